@@ -0,0 +1,54 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// With the `embed-assets` feature, lists every file under `assets/` and
+// `shaders/` as an `include_bytes!` table for src/resources.rs
+fn main() {
+    if env::var_os("CARGO_FEATURE_EMBED_ASSETS").is_none() {
+        return;
+    }
+
+    let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let mut files = Vec::new();
+    for directory in ["assets", "shaders"] {
+        println!("cargo:rerun-if-changed={}", directory);
+        collect(&root, &root.join(directory), &mut files);
+    }
+    files.sort();
+
+    let mut table = String::from("&[\n");
+    for (name, path) in files {
+        table.push_str(&format!(
+            "    ({:?}, include_bytes!({:?})),\n",
+            name,
+            path.display().to_string()
+        ));
+    }
+    table.push(']');
+
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("embedded.rs");
+    fs::write(out, table).unwrap();
+}
+
+fn collect(root: &Path, directory: &Path, files: &mut Vec<(String, PathBuf)>) {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect(root, &path, files);
+        } else {
+            // Looked up with the same forward slash paths the game uses
+            let name = path
+                .strip_prefix(root)
+                .unwrap()
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((name, path));
+        }
+    }
+}
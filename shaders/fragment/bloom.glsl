@@ -0,0 +1,14 @@
+#version 330 core
+in vec2 TexCoords;
+
+out vec4 FragColor;
+
+uniform sampler2D screenTexture;
+uniform sampler2D bloomTexture;
+uniform float intensity;
+
+void main() {
+    vec3 color = texture(screenTexture, TexCoords).rgb;
+    vec3 bloom = texture(bloomTexture, TexCoords).rgb;
+    FragColor = vec4(color + bloom * intensity, 1.0);
+}
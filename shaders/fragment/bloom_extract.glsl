@@ -0,0 +1,15 @@
+#version 330 core
+#include "color.glsl"
+
+in vec2 TexCoords;
+
+out vec4 FragColor;
+
+uniform sampler2D screenTexture;
+uniform float threshold;
+
+void main() {
+    vec3 color = texture(screenTexture, TexCoords).rgb;
+    float brightness = luminance(color);
+    FragColor = vec4(color * smoothstep(threshold, threshold + 0.2, brightness), 1.0);
+}
@@ -0,0 +1,21 @@
+#version 330 core
+in vec2 TexCoords;
+
+out vec4 FragColor;
+
+uniform sampler2D screenTexture;
+uniform bool horizontal;
+
+const float weights[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(screenTexture, 0));
+    vec2 offset = horizontal ? vec2(texel.x, 0.0) : vec2(0.0, texel.y);
+
+    vec3 result = texture(screenTexture, TexCoords).rgb * weights[0];
+    for (int i = 1; i < 5; i++) {
+        result += texture(screenTexture, TexCoords + offset * float(i)).rgb * weights[i];
+        result += texture(screenTexture, TexCoords - offset * float(i)).rgb * weights[i];
+    }
+    FragColor = vec4(result, 1.0);
+}
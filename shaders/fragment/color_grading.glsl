@@ -0,0 +1,19 @@
+#version 330 core
+#include "color.glsl"
+
+in vec2 TexCoords;
+
+out vec4 FragColor;
+
+uniform sampler2D screenTexture;
+uniform vec3 tint;
+uniform float exposure;
+uniform float contrast;
+uniform float saturation;
+
+void main() {
+    vec3 color = texture(screenTexture, TexCoords).rgb * exposure * tint;
+    color = (color - 0.5) * contrast + 0.5;
+    color = mix(vec3(luminance(color)), color, saturation);
+    FragColor = vec4(clamp(color, 0.0, 1.0), 1.0);
+}
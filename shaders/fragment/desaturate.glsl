@@ -0,0 +1,14 @@
+#version 330 core
+#include "color.glsl"
+
+in vec2 TexCoords;
+
+out vec4 FragColor;
+
+uniform sampler2D screenTexture;
+uniform float amount;
+
+void main() {
+    vec3 color = texture(screenTexture, TexCoords).rgb;
+    FragColor = vec4(mix(color, vec3(luminance(color)) * 0.8, amount), 1.0);
+}
@@ -1,12 +1,73 @@
 #version 330 core
+// Built with SHADOWS and FOG defined when those are enabled
+#include "lighting.glsl"
+#ifdef FOG
+#include "fog.glsl"
+#endif
+
 out vec4 FragColor;
 
 in vec3 Color;
 in vec2 TexCoords;
+in vec3 FragPos;
+in vec3 Normal;
+#ifdef SHADOWS
+in vec4 FragPosLightSpace;
+#endif
+flat in float Layer;
 
 uniform sampler2D texture_diffuse;
+uniform bool useTheme;
+uniform sampler2DArray themeTextures;
+
+uniform vec3 viewPos;
+uniform float shadowStrength;
+
+#ifdef SHADOWS
+uniform sampler2D shadowMap;
+
+float shadowFactor(vec3 normal, vec3 lightDir) {
+    vec3 coords = FragPosLightSpace.xyz / FragPosLightSpace.w * 0.5 + 0.5;
+    if (coords.z > 1.0)
+        return 0.0;
+
+    float bias = max(0.003 * (1.0 - dot(normal, lightDir)), 0.0005);
+    vec2 texelSize = 1.0 / vec2(textureSize(shadowMap, 0));
+    float shadow = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            float closest = texture(shadowMap, coords.xy + vec2(x, y) * texelSize).r;
+            shadow += coords.z - bias > closest ? 1.0 : 0.0;
+        }
+    }
+    return shadow / 9.0;
+}
+#else
+float shadowFactor(vec3 normal, vec3 lightDir) {
+    return 0.0;
+}
+#endif
 
 void main() {
-    vec4 texColor = texture(texture_diffuse, TexCoords);
-    FragColor = texColor;
-}
\ No newline at end of file
+    vec4 texColor = useTheme
+        ? texture(themeTextures, vec3(TexCoords, Layer))
+        : texture(texture_diffuse, TexCoords);
+
+    vec3 normal = normalize(Normal);
+    vec3 viewDir = normalize(viewPos - FragPos);
+    // Walls, floor and ceiling are single planes seen from either side
+    if (dot(normal, viewDir) < 0.0)
+        normal = -normal;
+
+    vec3 sunDir = normalize(-sunDirection);
+    float shadow = shadowFactor(normal, sunDir);
+    vec3 light = ambientColor * (1.0 - shadowStrength * shadow);
+    light += blinnPhong(normal, viewDir, sunDir, sunColor) * (1.0 - shadow);
+    light += pointLighting(normal, viewDir, FragPos);
+
+    vec3 color = texColor.rgb * light;
+#ifdef FOG
+    color = applyFog(color, length(viewPos - FragPos));
+#endif
+    FragColor = vec4(color, texColor.a);
+}
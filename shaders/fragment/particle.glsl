@@ -0,0 +1,18 @@
+#version 330 core
+in vec2 Corner;
+in vec4 Color;
+in vec4 UvRect;
+
+out vec4 FragColor;
+
+uniform sampler2D particleTexture;
+
+void main() {
+    if (UvRect.z > 0.0) {
+        vec4 texColor = texture(particleTexture, UvRect.xy + Corner * UvRect.zw);
+        FragColor = vec4(texColor.rgb, texColor.a) * Color;
+    } else {
+        float falloff = 1.0 - smoothstep(0.3, 0.5, length(Corner - 0.5));
+        FragColor = vec4(Color.rgb, Color.a * falloff);
+    }
+}
@@ -0,0 +1,21 @@
+#version 330 core
+in vec2 TexCoords;
+
+out vec4 FragColor;
+
+uniform sampler2D screenTexture;
+uniform float strength;
+
+const int SAMPLES = 8;
+
+void main() {
+    // Streak towards the centre of the screen, leaving the middle sharp
+    vec2 toCenter = vec2(0.5) - TexCoords;
+    vec2 offset = toCenter * strength * 0.04 * length(toCenter);
+
+    vec3 result = vec3(0.0);
+    for (int i = 0; i < SAMPLES; i++) {
+        result += texture(screenTexture, TexCoords + offset * float(i)).rgb;
+    }
+    FragColor = vec4(result / float(SAMPLES), 1.0);
+}
@@ -0,0 +1,27 @@
+#version 330 core
+in vec2 Ndc;
+
+out vec4 FragColor;
+
+uniform mat4 inverseViewProjection;
+uniform vec3 horizonColor;
+uniform vec3 topColor;
+uniform vec3 bottomColor;
+uniform bool useTexture;
+uniform sampler2D panorama;
+
+const float PI = 3.14159265;
+
+void main() {
+    vec4 far = inverseViewProjection * vec4(Ndc, 1.0, 1.0);
+    vec3 dir = normalize(far.xyz / far.w);
+
+    if (useTexture) {
+        vec2 uv = vec2(atan(dir.x, dir.z) / (2.0 * PI) + 0.5, asin(dir.y) / PI + 0.5);
+        FragColor = vec4(texture(panorama, uv).rgb, 1.0);
+    } else if (dir.y >= 0.0) {
+        FragColor = vec4(mix(horizonColor, topColor, smoothstep(0.0, 0.6, dir.y)), 1.0);
+    } else {
+        FragColor = vec4(mix(horizonColor, bottomColor, smoothstep(0.0, 0.3, -dir.y)), 1.0);
+    }
+}
@@ -1,13 +1,15 @@
 #version 330 core
 in vec2 TexCoords;
+flat in float Atlas;
 
 out vec4 FragColor;
 
 uniform sampler2D text;
+uniform sampler2D atlas;
 uniform vec3 textColor;
 
 void main()
 {
-    vec4 sampled = texture(text, TexCoords);
+    vec4 sampled = Atlas > 0.5 ? texture(atlas, TexCoords) : texture(text, TexCoords);
     FragColor = vec4(textColor, 1.0) * sampled;
 }
@@ -0,0 +1,12 @@
+#version 330 core
+in vec2 TexCoords;
+
+out vec4 FragColor;
+
+uniform sampler2D screenTexture;
+
+void main() {
+    vec3 color = texture(screenTexture, TexCoords).rgb;
+    float dist = length(TexCoords - vec2(0.5));
+    FragColor = vec4(color * (1.0 - smoothstep(0.4, 0.8, dist) * 0.6), 1.0);
+}
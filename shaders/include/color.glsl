@@ -0,0 +1,4 @@
+// Perceived brightness of a linear colour (Rec. 709 weights)
+float luminance(vec3 color) {
+    return dot(color, vec3(0.2126, 0.7152, 0.0722));
+}
@@ -0,0 +1,10 @@
+uniform vec3 fogColor;
+uniform float fogStart;
+uniform float fogDensity;
+
+// Exponential squared fog, starting at fogStart from the viewer
+vec3 applyFog(vec3 color, float viewDistance) {
+    float fogDistance = max(viewDistance - fogStart, 0.0) * fogDensity;
+    float fog = 1.0 - exp(-fogDistance * fogDistance);
+    return mix(color, fogColor, fog);
+}
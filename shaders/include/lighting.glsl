@@ -0,0 +1,34 @@
+#define MAX_POINT_LIGHTS 8
+
+struct PointLight {
+    vec3 position;
+    vec3 color;
+    float range;
+};
+
+uniform vec3 ambientColor;
+uniform vec3 sunDirection;
+uniform vec3 sunColor;
+uniform float shininess;
+uniform float specularStrength;
+uniform PointLight pointLights[MAX_POINT_LIGHTS];
+uniform int pointLightCount;
+
+vec3 blinnPhong(vec3 normal, vec3 viewDir, vec3 lightDir, vec3 lightColor) {
+    float diffuse = max(dot(normal, lightDir), 0.0);
+    vec3 halfway = normalize(lightDir + viewDir);
+    float specular = pow(max(dot(normal, halfway), 0.0), shininess) * specularStrength;
+    return lightColor * (diffuse + specular);
+}
+
+// Sum of the point lights reaching a fragment, fading out at their range
+vec3 pointLighting(vec3 normal, vec3 viewDir, vec3 fragPos) {
+    vec3 light = vec3(0.0);
+    for (int i = 0; i < pointLightCount; i++) {
+        vec3 toLight = pointLights[i].position - fragPos;
+        float lightDistance = length(toLight);
+        float falloff = clamp(1.0 - pow(lightDistance / pointLights[i].range, 2.0), 0.0, 1.0);
+        light += blinnPhong(normal, viewDir, toLight / lightDistance, pointLights[i].color) * falloff * falloff;
+    }
+    return light;
+}
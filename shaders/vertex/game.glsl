@@ -2,16 +2,34 @@
 layout (location = 0) in vec3 aPos;
 layout (location = 1) in vec3 aColor;
 layout (location = 2) in vec2 aTexCoords;
+layout (location = 3) in vec3 aNormal;
+layout (location = 4) in mat4 aModel;
+layout (location = 8) in float aLayer;
 
 out vec3 Color;
 out vec2 TexCoords;
+out vec3 FragPos;
+out vec3 Normal;
+#ifdef SHADOWS
+out vec4 FragPosLightSpace;
+#endif
+flat out float Layer;
 
-uniform mat4 model;
 uniform mat4 view;
 uniform mat4 projection;
+#ifdef SHADOWS
+uniform mat4 lightSpaceMatrix;
+#endif
 
 void main() {
-    gl_Position = projection * view * model * vec4(aPos, 1.0);
+    vec4 worldPos = aModel * vec4(aPos, 1.0);
+    gl_Position = projection * view * worldPos;
     Color = aColor;
     TexCoords = aTexCoords;
-}
\ No newline at end of file
+    FragPos = worldPos.xyz;
+    Normal = mat3(transpose(inverse(aModel))) * aNormal;
+#ifdef SHADOWS
+    FragPosLightSpace = lightSpaceMatrix * worldPos;
+#endif
+    Layer = aLayer;
+}
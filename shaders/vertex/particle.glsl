@@ -0,0 +1,24 @@
+#version 330 core
+layout (location = 0) in vec2 aCorner;
+layout (location = 1) in vec4 aPositionSize;
+layout (location = 2) in vec4 aColor;
+layout (location = 3) in vec4 aUvRect;
+
+out vec2 Corner;
+out vec4 Color;
+out vec4 UvRect;
+
+uniform mat4 view;
+uniform mat4 projection;
+
+void main() {
+    // Billboard: expand the quad along the camera's right and up axes
+    vec3 right = vec3(view[0][0], view[1][0], view[2][0]);
+    vec3 up = vec3(view[0][1], view[1][1], view[2][1]);
+    vec3 position = aPositionSize.xyz + (right * aCorner.x + up * aCorner.y) * aPositionSize.w;
+
+    gl_Position = projection * view * vec4(position, 1.0);
+    Corner = aCorner + 0.5;
+    Color = aColor;
+    UvRect = aUvRect;
+}
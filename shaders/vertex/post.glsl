@@ -0,0 +1,9 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+
+out vec2 TexCoords;
+
+void main() {
+    TexCoords = aPos.xy;
+    gl_Position = vec4(aPos.xy * 2.0 - 1.0, 0.0, 1.0);
+}
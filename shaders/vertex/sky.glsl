@@ -0,0 +1,9 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+
+out vec2 Ndc;
+
+void main() {
+    Ndc = aPos.xy * 2.0 - 1.0;
+    gl_Position = vec4(Ndc, 1.0, 1.0);
+}
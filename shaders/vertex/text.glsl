@@ -1,14 +1,18 @@
 #version 330 core
 layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec3 aColor;
 layout (location = 2) in vec2 aTexCoords;
 
 uniform mat4 projection;
 uniform mat4 model;
 
 out vec2 TexCoords;
+// 1 for glyphs from the glyph atlas, 0 for the bitmap font
+flat out float Atlas;
 
 void main()
 {
     gl_Position = projection * model * vec4(aPos, 1.0);
     TexCoords = aTexCoords;
+    Atlas = aColor.r;
 }
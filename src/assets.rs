@@ -0,0 +1,250 @@
+use crate::audio::{Sound, SoundError};
+use crate::texture::{Image, Texture, TextureArray, TextureError, TextureOptions};
+use std::cell::{Ref, RefCell};
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::{Rc, Weak};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+// Shared reference to an asset, which is freed once the last handle is dropped.
+// Until the file is decoded it resolves to a placeholder
+pub struct Handle<T>(Rc<Slot<T>>);
+
+type Slot<T> = RefCell<T>;
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Handle(Rc::clone(&self.0))
+    }
+}
+
+impl<T> Handle<T> {
+    fn new(placeholder: T) -> Self {
+        Handle(Rc::new(RefCell::new(placeholder)))
+    }
+
+    pub fn get(&self) -> Ref<'_, T> {
+        self.0.borrow()
+    }
+
+    fn weak(&self) -> Weak<Slot<T>> {
+        Rc::downgrade(&self.0)
+    }
+}
+
+fn fill<T>(slot: &Weak<Slot<T>>, asset: T) {
+    // Nobody holds the asset anymore, drop it right away
+    if let Some(slot) = slot.upgrade() {
+        *slot.borrow_mut() = asset;
+    }
+}
+
+enum Job {
+    Image(String),
+    Images(Vec<String>),
+    Sound(String),
+}
+
+enum Decoded {
+    Image(Result<Image, TextureError>),
+    Images(Vec<Result<Image, TextureError>>),
+    Sound(Result<Sound, SoundError>),
+}
+
+enum Pending {
+    Texture(Weak<Slot<Texture>>, TextureOptions),
+    TextureArray(Weak<Slot<TextureArray>>, TextureOptions),
+    Sound(Weak<Slot<Sound>>),
+}
+
+// Files are read and decoded on a worker thread and swapped into their handles in
+// `update`, textures reach the GPU on their first bind
+pub struct AssetManager {
+    jobs: Sender<(u64, Job)>,
+    results: Receiver<(u64, Decoded)>,
+    next_job: u64,
+    pending: HashMap<u64, Pending>,
+    // The first request for a path decides its options
+    textures: HashMap<String, (Weak<Slot<Texture>>, TextureOptions)>,
+    texture_arrays: HashMap<Vec<String>, (Weak<Slot<TextureArray>>, TextureOptions)>,
+    sounds: HashMap<String, Weak<Slot<Sound>>>,
+}
+
+impl AssetManager {
+    pub fn new() -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<(u64, Job)>();
+        let (result_sender, results) = mpsc::channel();
+
+        // Exits when the manager drops its sender
+        thread::spawn(move || {
+            for (id, job) in job_receiver {
+                let decoded = match job {
+                    Job::Image(path) => Decoded::Image(Image::load(&path)),
+                    Job::Images(paths) => {
+                        Decoded::Images(paths.iter().map(|path| Image::load(path)).collect())
+                    }
+                    Job::Sound(path) => Decoded::Sound(Sound::load(&path)),
+                };
+                if result_sender.send((id, decoded)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            jobs,
+            results,
+            next_job: 0,
+            pending: HashMap::new(),
+            textures: HashMap::new(),
+            texture_arrays: HashMap::new(),
+            sounds: HashMap::new(),
+        }
+    }
+
+    pub fn texture(&mut self, path: &str, options: &TextureOptions) -> Handle<Texture> {
+        if let Some(slot) = self.textures.get(path).and_then(|(slot, _)| slot.upgrade()) {
+            return Handle(slot);
+        }
+        let handle = Handle::new(Texture::placeholder());
+        self.textures.insert(path.into(), (handle.weak(), *options));
+        self.request(
+            Job::Image(path.into()),
+            Pending::Texture(handle.weak(), *options),
+        );
+        handle
+    }
+
+    // One layer per path, missing files become placeholder layers
+    pub fn texture_array(
+        &mut self,
+        paths: &[String],
+        options: &TextureOptions,
+    ) -> Handle<TextureArray> {
+        if let Some(slot) = self
+            .texture_arrays
+            .get(paths)
+            .and_then(|(slot, _)| slot.upgrade())
+        {
+            return Handle(slot);
+        }
+        let placeholders: Vec<Image> = paths.iter().map(|_| Image::placeholder()).collect();
+        let handle = Handle::new(TextureArray::new(&placeholders, options));
+        self.texture_arrays
+            .insert(paths.to_vec(), (handle.weak(), *options));
+        self.request(
+            Job::Images(paths.to_vec()),
+            Pending::TextureArray(handle.weak(), *options),
+        );
+        handle
+    }
+
+    pub fn sound(&mut self, path: &str) -> Handle<Sound> {
+        if let Some(slot) = self.sounds.get(path).and_then(Weak::upgrade) {
+            return Handle(slot);
+        }
+        let handle = Handle::new(Sound::silence());
+        self.sounds.insert(path.into(), handle.weak());
+        self.request(Job::Sound(path.into()), Pending::Sound(handle.weak()));
+        handle
+    }
+
+    // Decodes a changed file again into every asset built from it, which keep their handles
+    pub fn reload(&mut self, path: &Path) {
+        let mut requests = Vec::new();
+        for (key, (slot, options)) in &self.textures {
+            if Path::new(key) == path {
+                requests.push((
+                    Job::Image(key.clone()),
+                    Pending::Texture(slot.clone(), *options),
+                ));
+            }
+        }
+        for (paths, (slot, options)) in &self.texture_arrays {
+            if paths.iter().any(|key| Path::new(key) == path) {
+                requests.push((
+                    Job::Images(paths.clone()),
+                    Pending::TextureArray(slot.clone(), *options),
+                ));
+            }
+        }
+        for (key, slot) in &self.sounds {
+            if Path::new(key) == path {
+                requests.push((Job::Sound(key.clone()), Pending::Sound(slot.clone())));
+            }
+        }
+
+        for (job, pending) in requests {
+            self.request(job, pending);
+        }
+    }
+
+    fn request(&mut self, job: Job, pending: Pending) {
+        let id = self.next_job;
+        self.next_job += 1;
+        if self.jobs.send((id, job)).is_err() {
+            eprintln!("Asset loader stopped, keeping the placeholder");
+            return;
+        }
+        self.pending.insert(id, pending);
+    }
+
+    // Uploads whatever the worker has finished, called once per frame
+    pub fn update(&mut self) {
+        while let Ok((id, decoded)) = self.results.try_recv() {
+            self.finish(id, decoded);
+        }
+        self.forget_unused();
+    }
+
+    // Blocks until every request is done, so nothing is drawn with a placeholder
+    pub fn wait(&mut self) {
+        while !self.pending.is_empty() {
+            match self.results.recv() {
+                Ok((id, decoded)) => self.finish(id, decoded),
+                Err(_) => {
+                    eprintln!("Asset loader stopped, keeping the placeholders");
+                    self.pending.clear();
+                }
+            }
+        }
+        self.forget_unused();
+    }
+
+    fn finish(&mut self, id: u64, decoded: Decoded) {
+        let Some(pending) = self.pending.remove(&id) else {
+            return;
+        };
+        match (pending, decoded) {
+            (Pending::Texture(slot, options), Decoded::Image(image)) => match image {
+                Ok(image) => fill(&slot, Texture::from_image(image, &options)),
+                Err(e) => eprintln!("{}, using a placeholder", e),
+            },
+            (Pending::TextureArray(slot, options), Decoded::Images(images)) => {
+                let images: Vec<Image> = images
+                    .into_iter()
+                    .map(|image| {
+                        image.unwrap_or_else(|e| {
+                            eprintln!("{}, using a placeholder", e);
+                            Image::placeholder()
+                        })
+                    })
+                    .collect();
+                fill(&slot, TextureArray::new(&images, &options));
+            }
+            (Pending::Sound(slot), Decoded::Sound(sound)) => match sound {
+                Ok(sound) => fill(&slot, sound),
+                Err(e) => eprintln!("{}, playing silence", e),
+            },
+            _ => unreachable!("asset job answered with the wrong kind"),
+        }
+    }
+
+    fn forget_unused(&mut self) {
+        self.textures.retain(|_, (slot, _)| slot.strong_count() > 0);
+        self.texture_arrays
+            .retain(|_, (slot, _)| slot.strong_count() > 0);
+        self.sounds.retain(|_, slot| slot.strong_count() > 0);
+    }
+}
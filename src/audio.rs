@@ -1,14 +1,53 @@
+use crate::assets::{AssetManager, Handle};
+use crate::resources;
+use rodio::buffer::SamplesBuffer;
+use rodio::decoder::DecoderError;
 use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufReader, Cursor};
-use std::path::Path;
+use std::io::BufReader;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SoundError {
+    #[error("Failed to open sound {0}: {1}")]
+    Io(String, std::io::Error),
+    #[error("Failed to decode sound {0}: {1}")]
+    Decode(String, DecoderError),
+}
+
+// Fully decoded samples, so playing an effect does no file or decoder work
+pub struct Sound {
+    channels: u16,
+    sample_rate: u32,
+    samples: Vec<i16>,
+}
+
+impl Sound {
+    pub fn load(path: &str) -> Result<Self, SoundError> {
+        let file = resources::open(path).map_err(|e| SoundError::Io(path.into(), e))?;
+        let decoder =
+            Decoder::new(BufReader::new(file)).map_err(|e| SoundError::Decode(path.into(), e))?;
+        Ok(Sound {
+            channels: decoder.channels(),
+            sample_rate: decoder.sample_rate(),
+            samples: decoder.collect(),
+        })
+    }
+
+    pub fn silence() -> Self {
+        Sound {
+            channels: 1,
+            sample_rate: 44100,
+            samples: Vec::new(),
+        }
+    }
+}
 
 pub struct AudioSystem {
     _stream: Option<OutputStream>,
     stream_handle: Option<OutputStreamHandle>,
     music_sink: Option<Sink>,
-    sound_effects: HashMap<String, Vec<u8>>,
+    sound_effects: HashMap<String, Handle<Sound>>,
     sound_volume: f32,
     music_volume: f32,
 }
@@ -22,14 +61,7 @@ impl AudioSystem {
                     "Audio initialization failed: {}. Continuing without audio.",
                     e
                 );
-                return AudioSystem {
-                    _stream: None,
-                    stream_handle: None,
-                    music_sink: None,
-                    sound_effects: HashMap::new(),
-                    sound_volume: 1.0,
-                    music_volume: 1.0,
-                };
+                return Self::silent();
             }
         };
 
@@ -61,16 +93,25 @@ impl AudioSystem {
         }
     }
 
+    // Plays nothing, without opening an output device
+    pub fn silent() -> Self {
+        AudioSystem {
+            _stream: None,
+            stream_handle: None,
+            music_sink: None,
+            sound_effects: HashMap::new(),
+            sound_volume: 1.0,
+            music_volume: 1.0,
+        }
+    }
+
     // Preload sound effects
-    pub fn load_sound(&mut self, name: &str, path: &str) {
+    pub fn load_sound(&mut self, name: &str, path: &str, assets: &mut AssetManager) {
         if self.music_sink.is_none() {
             return;
         }
-        let file = File::open(Path::new(path)).unwrap();
-        let mut reader = BufReader::new(file);
-        let mut buffer = Vec::new();
-        std::io::Read::read_to_end(&mut reader, &mut buffer).unwrap();
-        self.sound_effects.insert(name.to_string(), buffer);
+        self.sound_effects
+            .insert(name.to_string(), assets.sound(path));
     }
 
     pub fn play_music(&mut self, path: &str) {
@@ -80,7 +121,7 @@ impl AudioSystem {
                 Ok(sink) => {
                     sink.set_volume(self.music_volume);
                     self.music_sink = Some(sink);
-                    match File::open(path) {
+                    match resources::open(path) {
                         Ok(file) => {
                             let reader = BufReader::new(file);
                             match Decoder::new(reader) {
@@ -101,22 +142,24 @@ impl AudioSystem {
     }
 
     pub fn play_sound(&self, name: &str) {
-        if let Some(data) = self.sound_effects.get(name) {
-            let cursor = Cursor::new(data.clone());
-            match Decoder::new(cursor) {
-                Ok(source) => {
-                    if let Some(stream_handle) = &self.stream_handle {
-                        match Sink::try_new(stream_handle) {
-                            Ok(sink) => {
-                                sink.set_volume(self.sound_volume);
-                                sink.append(source);
-                                sink.detach();
-                            }
-                            Err(e) => eprintln!("Failed to play sound: {}", e),
-                        }
+        if let Some(sound) = self.sound_effects.get(name) {
+            let sound = sound.get();
+            if sound.samples.is_empty() {
+                return;
+            }
+            if let Some(stream_handle) = &self.stream_handle {
+                match Sink::try_new(stream_handle) {
+                    Ok(sink) => {
+                        sink.set_volume(self.sound_volume);
+                        sink.append(SamplesBuffer::new(
+                            sound.channels,
+                            sound.sample_rate,
+                            sound.samples.clone(),
+                        ));
+                        sink.detach();
                     }
+                    Err(e) => eprintln!("Failed to play sound: {}", e),
                 }
-                Err(e) => eprintln!("Failed to decode sound '{}': {}", name, e),
             }
         }
     }
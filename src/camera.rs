@@ -0,0 +1,253 @@
+use crate::character::Character;
+use crate::game::{MAX_SPEED, START_SPEED};
+use crate::math;
+use nalgebra::{Matrix4, Vector3};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum CameraMode {
+    #[default]
+    ThirdPerson,
+    FirstPerson,
+    TopDown,
+    Cinematic,
+}
+
+impl CameraMode {
+    pub fn next(self) -> Self {
+        match self {
+            CameraMode::ThirdPerson => CameraMode::FirstPerson,
+            CameraMode::FirstPerson => CameraMode::TopDown,
+            CameraMode::TopDown => CameraMode::Cinematic,
+            CameraMode::Cinematic => CameraMode::ThirdPerson,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct CameraSettings {
+    pub height: f32,
+    pub distance: f32,
+    pub look_height: f32,
+    pub follow: f32,
+    pub follow_speed: f32,
+    pub base_fov: f32,
+    pub max_fov: f32,
+    pub slide_dip: f32,
+    pub shake_strength: f32,
+    pub shake_decay: f32,
+    pub near_miss_shake: f32,
+    pub collision_shake: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            height: 3.0,
+            distance: 10.0,
+            look_height: 1.5,
+            follow: 0.5,
+            follow_speed: 4.0,
+            base_fov: 45.0,
+            max_fov: 60.0,
+            slide_dip: 0.6,
+            shake_strength: 0.4,
+            shake_decay: 1.5,
+            near_miss_shake: 0.35,
+            collision_shake: 1.0,
+        }
+    }
+}
+
+pub struct FreeFly {
+    position: Vector3<f32>,
+    yaw: f32,
+    pitch: f32,
+    last_mouse: Option<(f32, f32)>,
+}
+
+pub struct Camera {
+    settings: CameraSettings,
+    mode: CameraMode,
+    free: Option<FreeFly>,
+    character_position: Vector3<f32>,
+    character_height: f32,
+    x: f32,
+    dip: f32,
+    fov: f32,
+    trauma: f32,
+    time: f32,
+}
+
+fn smooth(current: f32, target: f32, speed: f32, delta_time: f32) -> f32 {
+    current + (target - current) * (1.0 - (-speed * delta_time).exp())
+}
+
+impl Camera {
+    const FREE_FLY_SPEED: f32 = 10.0;
+    const MOUSE_SENSITIVITY: f32 = 0.003;
+
+    pub fn new(settings: CameraSettings, mode: CameraMode) -> Self {
+        Self {
+            fov: settings.base_fov,
+            settings,
+            mode,
+            free: None,
+            character_position: Vector3::zeros(),
+            character_height: 1.0,
+            x: 0.0,
+            dip: 0.0,
+            trauma: 0.0,
+            time: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, character: &Character, speed: f32, delta_time: f32) {
+        let s = &self.settings;
+        self.time += delta_time;
+        self.character_position = character.position.coords;
+        self.character_height = character.current_height;
+
+        // Follow the character laterally
+        let target_x = character.position.x * s.follow;
+        self.x = smooth(self.x, target_x, s.follow_speed, delta_time);
+
+        // Widen the FOV with speed
+        let speed_factor = ((speed - START_SPEED) / (MAX_SPEED - START_SPEED)).clamp(0.0, 1.0);
+        let target_fov = s.base_fov + (s.max_fov - s.base_fov) * speed_factor;
+        self.fov = smooth(self.fov, target_fov, 2.0, delta_time);
+
+        // Dip while sliding
+        let target_dip = if character.is_sliding() {
+            s.slide_dip
+        } else {
+            0.0
+        };
+        self.dip = smooth(self.dip, target_dip, 8.0, delta_time);
+
+        self.trauma = (self.trauma - s.shake_decay * delta_time).max(0.0);
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        self.mode = mode;
+    }
+
+    pub fn next_mode(&mut self) {
+        self.mode = self.mode.next();
+    }
+
+    pub fn enter_free_fly(&mut self) {
+        let eye = self.eye();
+        let forward = (self.target() - eye).normalize();
+        self.free = Some(FreeFly {
+            position: eye,
+            yaw: forward.x.atan2(forward.z),
+            pitch: forward.y.asin(),
+            last_mouse: None,
+        });
+    }
+
+    pub fn exit_free_fly(&mut self) {
+        self.free = None;
+    }
+
+    // Moves the free-fly camera; `movement` is (right, up, forward) in the range -1..1
+    pub fn fly(&mut self, movement: Vector3<f32>, mouse: (f32, f32), delta_time: f32) {
+        let Some(free) = &mut self.free else {
+            return;
+        };
+
+        if let Some((last_x, last_y)) = free.last_mouse {
+            free.yaw -= (mouse.0 - last_x) * Self::MOUSE_SENSITIVITY;
+            free.pitch =
+                (free.pitch + (mouse.1 - last_y) * Self::MOUSE_SENSITIVITY).clamp(-1.5, 1.5);
+        }
+        free.last_mouse = Some(mouse);
+
+        let forward = free_forward(free.yaw, free.pitch);
+        let right = forward.cross(&Vector3::y()).normalize();
+        let velocity = right * movement.x + Vector3::y() * movement.y + forward * movement.z;
+        free.position += velocity * Self::FREE_FLY_SPEED * delta_time;
+    }
+
+    pub fn near_miss(&mut self) {
+        self.add_shake(self.settings.near_miss_shake);
+    }
+
+    pub fn collision(&mut self) {
+        self.add_shake(self.settings.collision_shake);
+    }
+
+    pub fn add_shake(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.0);
+    }
+
+    fn shake_offset(&self) -> Vector3<f32> {
+        let amount = self.settings.shake_strength * self.trauma * self.trauma;
+        let t = self.time * 40.0;
+        Vector3::new(
+            amount * ((t * 1.1).sin() + (t * 2.3).sin() * 0.5),
+            amount * ((t * 1.7).sin() + (t * 2.9).cos() * 0.5),
+            0.0,
+        )
+    }
+
+    pub fn eye(&self) -> Vector3<f32> {
+        if let Some(free) = &self.free {
+            return free.position;
+        }
+
+        let s = &self.settings;
+        let eye = match self.mode {
+            CameraMode::ThirdPerson => Vector3::new(self.x, s.height - self.dip, -s.distance),
+            CameraMode::FirstPerson => Vector3::new(
+                self.character_position.x,
+                self.character_position.y + self.character_height * 0.8,
+                0.55,
+            ),
+            CameraMode::TopDown => Vector3::new(self.x * 0.5, 4.7 - self.dip, -1.5),
+            CameraMode::Cinematic => Vector3::new(2.5, 2.2 - self.dip, -3.0),
+        };
+        eye + self.shake_offset()
+    }
+
+    fn target(&self) -> Vector3<f32> {
+        if let Some(free) = &self.free {
+            return free.position + free_forward(free.yaw, free.pitch);
+        }
+
+        let s = &self.settings;
+        let target = match self.mode {
+            CameraMode::ThirdPerson => Vector3::new(self.x, s.look_height - self.dip * 0.5, 0.0),
+            CameraMode::FirstPerson => Vector3::new(
+                self.character_position.x,
+                self.character_position.y + self.character_height * 0.7,
+                10.0,
+            ),
+            CameraMode::TopDown => Vector3::new(self.x * 0.5, 0.0, 4.0),
+            CameraMode::Cinematic => Vector3::new(self.x - 0.5, 1.0, 5.0),
+        };
+        target + self.shake_offset()
+    }
+
+    pub fn view(&self) -> Matrix4<f32> {
+        math::look_at(self.eye(), self.target(), Vector3::new(0.0, 1.0, 0.0))
+    }
+
+    pub fn projection(&self, aspect: f32, far: f32) -> Matrix4<f32> {
+        math::perspective(self.fov.to_radians(), aspect, 0.1, far)
+    }
+}
+
+fn free_forward(yaw: f32, pitch: f32) -> Vector3<f32> {
+    Vector3::new(
+        yaw.sin() * pitch.cos(),
+        pitch.sin(),
+        yaw.cos() * pitch.cos(),
+    )
+}
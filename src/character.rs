@@ -11,6 +11,7 @@ pub struct Character {
     pub position: Point3<f32>,
     velocity: Vector3<f32>,
     is_grounded: bool,
+    landed: bool,
     is_pressing_down: bool,
     lane: i8,
     target_x: f32,
@@ -25,16 +26,17 @@ fn lerp(a: f32, b: f32, t: f32) -> f32 {
 }
 
 impl Character {
-    const JUMP_FORCE: f32 = 8.0;
-    const BASE_GRAVITY: f32 = -16.0;
-    const FAST_FALL_GRAVITY: f32 = -100.0;
-    const LANE_CHANGE_SPEED: f32 = 5.0;
+    const JUMP_FORCE: f32 = 16.0;
+    const BASE_GRAVITY: f32 = -64.0;
+    const FAST_FALL_GRAVITY: f32 = -400.0;
+    const LANE_CHANGE_SPEED: f32 = 10.0;
 
     pub fn new() -> Self {
         Self {
             position: Point3::new(0.0, 0.0, 0.0),
             velocity: Vector3::zeros(),
             is_grounded: true,
+            landed: false,
             is_pressing_down: false,
             lane: 0,
             target_x: 0.0,
@@ -75,6 +77,9 @@ impl Character {
 
         // Ground collision
         if self.position.y <= 0.0 {
+            if !self.is_grounded {
+                self.landed = true;
+            }
             self.position.y = 0.0;
             self.velocity.y = 0.0;
             self.is_grounded = true;
@@ -100,6 +105,15 @@ impl Character {
         }
     }
 
+    // Returns true once after each landing
+    pub fn take_landed(&mut self) -> bool {
+        std::mem::take(&mut self.landed)
+    }
+
+    pub fn is_sliding(&self) -> bool {
+        self.is_grounded && self.is_pressing_down
+    }
+
     pub fn move_down(&mut self, state: bool) {
         self.is_pressing_down = state;
     }
@@ -139,4 +153,11 @@ impl AABB {
             && self.min.z <= other.max.z
             && self.max.z >= other.min.z
     }
+
+    pub fn expanded(&self, margin: f32) -> AABB {
+        AABB {
+            min: self.min - Vector3::repeat(margin),
+            max: self.max + Vector3::repeat(margin),
+        }
+    }
 }
@@ -2,7 +2,7 @@ use crate::character::Character;
 use crate::new_game;
 use crate::GameState;
 use crate::WorldState;
-use glfw::{Action, Key, WindowEvent};
+use glfw::{Action, CursorMode, Key, WindowEvent};
 
 pub fn handle_keys(
     window: &mut glfw::Window,
@@ -21,6 +21,15 @@ pub fn handle_keys(
                     world.pause_start_time = glfw.get_time();
                     character.move_down(false);
                 }
+                Key::C if action == Action::Press => world.run.camera.next_mode(),
+                Key::F1 if action == Action::Press => {
+                    *game_state = GameState::FreeCamera;
+                    world.pause_start_time = glfw.get_time();
+                    world.audio.pause_music();
+                    character.move_down(false);
+                    world.run.camera.enter_free_fly();
+                    window.set_cursor_mode(CursorMode::Disabled);
+                }
                 Key::Left | Key::A if action == Action::Press => character.move_left(&world.audio),
                 Key::Right | Key::D if action == Action::Press => {
                     character.move_right(&world.audio)
@@ -44,6 +53,15 @@ pub fn handle_keys(
                 }
                 _ => {}
             },
+            GameState::FreeCamera => match key {
+                Key::Escape | Key::F1 if action == Action::Press => {
+                    *game_state = GameState::Playing;
+                    world.audio.resume_music();
+                    world.run.camera.exit_free_fly();
+                    window.set_cursor_mode(CursorMode::Normal);
+                }
+                _ => {}
+            },
             GameState::Menu => match key {
                 Key::Escape if action == Action::Press => {
                     world.audio.play_sound("button1");
@@ -55,7 +73,7 @@ pub fn handle_keys(
                 }
                 _ => {}
             },
-            GameState::MapSelect | GameState::SkinSelect => match key {
+            GameState::MapSelect | GameState::SkinSelect | GameState::Settings => match key {
                 Key::Escape if action == Action::Press => {
                     world.audio.play_sound("button1");
                     *game_state = GameState::Menu;
@@ -0,0 +1,263 @@
+use crate::texture::{Image, Texture, TextureOptions};
+use gl::types::*;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum FramebufferError {
+    #[error("Framebuffer objects are not supported by this OpenGL context")]
+    Unsupported,
+    #[error("Framebuffer is incomplete (status 0x{0:x})")]
+    Incomplete(GLenum),
+}
+
+pub struct Framebuffer {
+    pub id: GLuint,
+    pub width: i32,
+    pub height: i32,
+    pub color: Option<Texture>,
+    pub depth: Option<Texture>,
+    renderbuffer: GLuint,
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.id);
+            if self.renderbuffer != 0 {
+                gl::DeleteRenderbuffers(1, &self.renderbuffer);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum RenderTarget<'a> {
+    Screen { width: f32, height: f32 },
+    Framebuffer(&'a Framebuffer),
+}
+
+impl RenderTarget<'_> {
+    pub fn bind(&self) {
+        match self {
+            RenderTarget::Screen { width, height } => Framebuffer::bind_default(*width, *height),
+            RenderTarget::Framebuffer(framebuffer) => framebuffer.bind(),
+        }
+    }
+}
+
+impl Framebuffer {
+    pub fn supported() -> bool {
+        gl::GenFramebuffers::is_loaded()
+            && gl::BindFramebuffer::is_loaded()
+            && gl::FramebufferTexture2D::is_loaded()
+            && gl::CheckFramebufferStatus::is_loaded()
+    }
+
+    pub fn depth_only(width: i32, height: i32) -> Result<Self, FramebufferError> {
+        if !Self::supported() {
+            return Err(FramebufferError::Unsupported);
+        }
+
+        let mut id = 0;
+        let mut depth_id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut depth_id);
+            gl::BindTexture(gl::TEXTURE_2D, depth_id);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::DEPTH_COMPONENT24 as i32,
+                width,
+                height,
+                0,
+                gl::DEPTH_COMPONENT,
+                gl::FLOAT,
+                std::ptr::null(),
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+            // Everything outside the map is lit
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_S,
+                gl::CLAMP_TO_BORDER as i32,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_T,
+                gl::CLAMP_TO_BORDER as i32,
+            );
+            let border = [1.0f32; 4];
+            gl::TexParameterfv(gl::TEXTURE_2D, gl::TEXTURE_BORDER_COLOR, border.as_ptr());
+
+            gl::GenFramebuffers(1, &mut id);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, id);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_ATTACHMENT,
+                gl::TEXTURE_2D,
+                depth_id,
+                0,
+            );
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+        }
+
+        let framebuffer = Framebuffer {
+            id,
+            width,
+            height,
+            color: None,
+            depth: Some(Texture::from_id(depth_id)),
+            renderbuffer: 0,
+        };
+        framebuffer.check()?;
+        Ok(framebuffer)
+    }
+
+    // Floating point colour target, with a depth renderbuffer for 3D passes
+    pub fn color(width: i32, height: i32, with_depth: bool) -> Result<Self, FramebufferError> {
+        if !Self::supported() {
+            return Err(FramebufferError::Unsupported);
+        }
+
+        let mut id = 0;
+        let mut color_id = 0;
+        let mut renderbuffer = 0;
+        unsafe {
+            gl::GenTextures(1, &mut color_id);
+            gl::BindTexture(gl::TEXTURE_2D, color_id);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA16F as i32,
+                width,
+                height,
+                0,
+                gl::RGBA,
+                gl::FLOAT,
+                std::ptr::null(),
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+
+            gl::GenFramebuffers(1, &mut id);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, id);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                color_id,
+                0,
+            );
+
+            if with_depth {
+                gl::GenRenderbuffers(1, &mut renderbuffer);
+                gl::BindRenderbuffer(gl::RENDERBUFFER, renderbuffer);
+                gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH_COMPONENT24, width, height);
+                gl::FramebufferRenderbuffer(
+                    gl::FRAMEBUFFER,
+                    gl::DEPTH_ATTACHMENT,
+                    gl::RENDERBUFFER,
+                    renderbuffer,
+                );
+            }
+        }
+
+        let framebuffer = Framebuffer {
+            id,
+            width,
+            height,
+            color: Some(Texture::from_id(color_id)),
+            depth: None,
+            renderbuffer,
+        };
+        framebuffer.check()?;
+        Ok(framebuffer)
+    }
+
+    fn check(&self) -> Result<(), FramebufferError> {
+        let status = unsafe {
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            status
+        };
+        if status == gl::FRAMEBUFFER_COMPLETE {
+            Ok(())
+        } else {
+            Err(FramebufferError::Incomplete(status))
+        }
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            gl::Viewport(0, 0, self.width, self.height);
+        }
+    }
+
+    pub fn bind_default(screen_width: f32, screen_height: f32) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(0, 0, screen_width as i32, screen_height as i32);
+        }
+    }
+
+    // What the window shows, read back before the buffers are swapped
+    pub fn read_default(screen_width: f32, screen_height: f32) -> Image {
+        let (width, height) = (screen_width as u32, screen_height as u32);
+        let mut pixels = vec![0; (width * height * 4) as usize];
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut _,
+            );
+        }
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    // Stretches an image rendered on the CPU over the window
+    pub fn present(image: &Image, screen_width: f32, screen_height: f32) {
+        let texture = Texture::from_image(image.clone(), &TextureOptions::ui());
+        let mut id = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut id);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, id);
+            gl::FramebufferTexture2D(
+                gl::READ_FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                texture.id(),
+                0,
+            );
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
+            gl::BlitFramebuffer(
+                0,
+                0,
+                image.width as i32,
+                image.height as i32,
+                0,
+                0,
+                screen_width as i32,
+                screen_height as i32,
+                gl::COLOR_BUFFER_BIT,
+                gl::NEAREST,
+            );
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::DeleteFramebuffers(1, &id);
+        }
+    }
+}
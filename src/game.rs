@@ -1,13 +1,123 @@
+use crate::assets::Handle;
+use crate::camera::Camera;
 use crate::character::Character;
-use crate::level::ObstacleType;
-use crate::math;
-use crate::mesh::Mesh;
-use crate::shader::Shader;
+use crate::level::{Obstacle, ObstacleType};
+use crate::map_config::MapConfig;
+use crate::math::{self, Frustum};
+use crate::mesh::{Instance, Mesh};
+use crate::mesh_registry::{MeshHandle, MeshRegistry};
+use crate::model::ObstacleMeshes;
+use crate::particles::ParticleSystem;
+use crate::postprocess::PostParams;
+use crate::renderer::{Material, Renderer, SceneSetup};
+use crate::settings::Settings;
+use crate::shadow::ShadowMap;
+use crate::texture::Texture;
+use crate::theme::{Layer, Theme};
+use crate::ui::{Anchor, Label};
 use crate::GameState;
 use crate::LevelGenerator;
 use crate::Maps;
 use crate::WorldState;
-use nalgebra::Vector3;
+use nalgebra::{Matrix4, Point3, Vector3};
+
+pub const START_SPEED: f32 = 20.0;
+pub const MAX_SPEED: f32 = 50.0;
+const NEAR_MISS_MARGIN: f32 = 0.35;
+
+// Everything a run needs to advance, without the window, sound or GL state,
+// so the same seed and ticks always give the same frame
+pub struct Run {
+    pub z: f32,
+    pub speed: f32,
+    pub level: LevelGenerator,
+    pub camera: Camera,
+}
+
+pub enum Collision {
+    None,
+    NearMiss,
+    Hit,
+}
+
+impl Run {
+    pub fn new(seed: u64, camera: Camera) -> Self {
+        Self {
+            z: 0.0,
+            speed: START_SPEED,
+            level: LevelGenerator::with_seed(seed),
+            camera,
+        }
+    }
+
+    pub fn advance(&mut self, character: &mut Character, delta_time: f32) -> Collision {
+        let previous_z = self.z;
+        self.z += self.speed * delta_time;
+        self.level.update(self.z);
+        self.speed = (self.speed + 0.3 * delta_time).min(MAX_SPEED);
+
+        character.update(delta_time);
+
+        let collision = self.detect_collisions(character, previous_z);
+        match collision {
+            Collision::Hit => self.camera.collision(),
+            Collision::NearMiss => self.camera.near_miss(),
+            Collision::None => {}
+        }
+        self.camera.update(character, self.speed, delta_time);
+        collision
+    }
+
+    fn detect_collisions(&self, character: &Character, previous_z: f32) -> Collision {
+        let mut near_miss = false;
+        let player_aabb = character.get_aabb(self.z);
+        let near_miss_aabb = player_aabb.expanded(NEAR_MISS_MARGIN);
+
+        for segment in self.level.segments() {
+            for obstacle in &segment.obstacles {
+                let obstacle_aabb = obstacle.get_aabb();
+                if player_aabb.collides(&obstacle_aabb) {
+                    return Collision::Hit;
+                } else if obstacle.position.z > previous_z
+                    && obstacle.position.z <= self.z
+                    && near_miss_aabb.collides(&obstacle_aabb)
+                {
+                    near_miss = true;
+                }
+            }
+        }
+
+        if near_miss {
+            Collision::NearMiss
+        } else {
+            Collision::None
+        }
+    }
+}
+
+// What `draw_world` needs from the world
+pub struct Scene<'a> {
+    pub run: &'a Run,
+    pub character: &'a Character,
+    pub map_config: &'a MapConfig,
+    pub meshes: &'a MeshRegistry,
+    pub platform_mesh: MeshHandle,
+    pub wall_mesh: MeshHandle,
+    pub obstacle_meshes: &'a ObstacleMeshes,
+    pub character_mesh: MeshHandle,
+    pub settings: &'a Settings,
+    pub theme: &'a Theme,
+    pub skin: &'a Handle<Texture>,
+    pub panorama: Option<&'a Handle<Texture>>,
+    pub particles: &'a ParticleSystem,
+    pub desaturation: f32,
+}
+
+impl Scene<'_> {
+    fn obstacle_mesh(&self, obstacle_type: &ObstacleType) -> &Mesh {
+        self.meshes.get(self.obstacle_meshes.get(obstacle_type))
+    }
+}
 
 pub fn new_game(
     game_state: &mut GameState,
@@ -15,153 +125,40 @@ pub fn new_game(
     world: &mut WorldState,
     glfw: &glfw::Glfw,
 ) {
-    world.z = 0.0;
-    world.speed = 20.0;
+    let camera = Camera::new(world.map_config.camera.clone(), world.run.camera.mode());
+    world.run = Run::new(rand::random(), camera);
     *character = Character::new();
-    world.level = LevelGenerator::new();
+    world.particles.clear();
     *game_state = GameState::Playing;
     world.total_pause_time = 0.0;
     world.pause_start_time = 0.0;
     world.last_frame_time = glfw.get_time();
     world.record = false;
+    world.desaturation = 0.0;
+    // The run starts with the map and skin picked in the menus fully loaded
+    world.assets.wait();
 }
 
 pub fn play(
     world: &mut WorldState,
     character: &mut Character,
     game_state: &mut GameState,
-    game_shader: &Shader,
-    character_mesh: &Mesh,
-    text_shader: &Shader,
     delta_time: f32,
 ) {
-    world.z += world.speed * delta_time;
-    world.level.update(world.z);
-    world.speed = (world.speed + 0.3 * delta_time).min(50.0);
-
-    character.update(delta_time);
-
-    let mut collision_detected = false;
-    let player_aabb = character.get_aabb(world.z);
-
-    unsafe {
-        gl::ClearColor(0.1, 0.1, 0.1, 1.0);
-        gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-
-        // Camera setup
-        let eye = Vector3::new(0.0, 3.0, -10.0);
-        let target = Vector3::new(0.0, 1.5, 0.0);
-        let view = math::look_at(eye, target, Vector3::new(0.0, 1.0, 0.0));
-        let projection = math::perspective(
-            45.0f32.to_radians(),
-            world.screen_width / world.screen_height,
-            0.1,
-            1000.0,
-        );
-
-        game_shader.use_program();
-        game_shader.set_mat4("view", &view);
-        game_shader.set_mat4("projection", &projection);
-
-        // Render level segments
-        for segment in world.level.segments() {
-            let segment_z = segment.position - world.z;
-            if segment_z < -25.0 {
-                continue;
-            }
-
-            // Platform rendering
-            let model = math::translation(0.0, 0.0, segment_z);
-            game_shader.set_mat4("model", &model);
-            game_shader.set_int("texture_diffuse", 0);
-            world.textures["floor"].bind(0);
-            segment.platform.draw();
-
-            // Left wall
-            let wall_model = math::translation(3.0, 0.0, segment_z);
-            game_shader.set_mat4("model", &wall_model);
-            world.textures["wall"].bind(0);
-            segment.wall.draw();
-
-            // Right wall
-            let wall_model = math::translation(-3.0, 0.0, segment_z);
-            game_shader.set_mat4("model", &wall_model);
-            segment.wall.draw();
-
-            // Ceiling
-            let ceiling_model = math::translation(0.0, 5.0, segment_z);
-            game_shader.set_mat4("model", &ceiling_model);
-            world.textures["ceiling"].bind(0);
-            segment.platform.draw();
-
-            // Obstacle handling
-            for obstacle in &segment.obstacles {
-                let obstacle_aabb = obstacle.get_aabb();
-                if player_aabb.collides(&obstacle_aabb) {
-                    let score = world.z as i32 / 10;
-                    if score >= 350 {
-                        world.audio.play_sound("collision2");
-                    } else {
-                        world.audio.play_sound("collision1");
-                    }
-                    world.audio.stop_music();
-                    world.current_music = None;
-                    collision_detected = true;
-                }
-
-                let obstacle_z = obstacle.position.z - world.z;
-                if obstacle_z < -25.0 {
-                    continue;
-                }
-
-                let model = math::translation(obstacle.position.x, obstacle.position.y, obstacle_z);
-                game_shader.set_mat4("model", &model);
-                match obstacle.obstacle_type {
-                    ObstacleType::Cube => world.textures["cube"].bind(0),
-                    ObstacleType::LowBar => world.textures["lowBar"].bind(0),
-                    ObstacleType::TallWall => world.textures["tallWall"].bind(0),
-                    ObstacleType::HighBar => world.textures["highBar"].bind(0),
-                }
-                obstacle.mesh.draw();
-            }
+    let collision_detected = matches!(world.run.advance(character, delta_time), Collision::Hit);
+    let score = world.run.z as i32 / 10;
+    if collision_detected {
+        if score >= 350 {
+            world.audio.play_sound("collision2");
+        } else {
+            world.audio.play_sound("collision1");
         }
-
-        // Character rendering
-        let model = math::translation(character.position.x, character.position.y + 0.001, 0.0)
-            * math::scaling(1.0, character.current_height, 1.0);
-        game_shader.set_mat4("model", &model);
-        world.textures["skin"].bind(0);
-        character_mesh.draw();
-
-        // Distance rendering
-        gl::Disable(gl::DEPTH_TEST);
-        gl::Enable(gl::BLEND);
-        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
-
-        let distance_text = format!("{}m", world.z as i32 / 10);
-        let text_mesh = Mesh::text(&distance_text);
-
-        text_shader.use_program();
-        let ui_projection =
-            math::orthographic(0.0, world.screen_width, 0.0, world.screen_height, -1.0, 1.0);
-        text_shader.set_mat4("projection", &ui_projection);
-        world.textures["font"].bind(0);
-        text_shader.set_vec3("textColor", &Vector3::new(0.9, 0.9, 0.9));
-
-        let text_scale = 40.0;
-        let text_model = math::translation(10.0, world.screen_height - 50.0, 0.0)
-            * math::scaling(text_scale, text_scale, 1.0);
-
-        text_shader.set_mat4("model", &text_model);
-        text_mesh.draw();
-
-        gl::Disable(gl::BLEND);
-        gl::Enable(gl::DEPTH_TEST);
+        world.audio.stop_music();
+        world.current_music = None;
     }
+    update_particles(world, character, collision_detected, delta_time);
 
     if collision_detected {
-        let score = world.z as i32 / 10;
-
         // Update deaths and unlock troll skin
         if !world.unlocked_skins["troll"] {
             let deaths = world.quest_progress.entry("deaths".into()).or_insert(0);
@@ -225,3 +222,195 @@ pub fn play(
         *game_state = GameState::GameOver;
     }
 }
+
+fn update_particles(
+    world: &mut WorldState,
+    character: &mut Character,
+    collision_detected: bool,
+    delta_time: f32,
+) {
+    let feet = Vector3::new(character.position.x, character.position.y, world.run.z);
+    if character.take_landed() {
+        world.particles.burst(&world.effects.landing_dust, feet);
+    }
+    if character.is_sliding() {
+        world
+            .slide_sparks
+            .update(&mut world.particles, feet, delta_time);
+    }
+    if collision_detected {
+        world.particles.burst(&world.effects.death_burst, feet);
+    }
+    for emitter in &mut world.ambient_particles {
+        emitter.update(
+            &mut world.particles,
+            Vector3::new(0.0, 0.0, world.run.z),
+            delta_time,
+        );
+    }
+    world.particles.update(delta_time);
+}
+
+// Distance counter drawn over the scene while playing
+pub struct Hud {
+    distance: i32,
+    label: Label,
+}
+
+impl Hud {
+    pub fn new() -> Self {
+        Hud {
+            distance: 0,
+            label: Label::new("0m", Anchor::TopLeft, (10.0, -10.0), 40.0)
+                .color(Vector3::new(0.9, 0.9, 0.9)),
+        }
+    }
+
+    pub fn render(&mut self, renderer: &mut dyn Renderer, run: &Run) {
+        // The text changes every ten units, not every frame
+        let distance = run.z as i32 / 10;
+        if distance != self.distance {
+            self.distance = distance;
+            self.label.set_text(&format!("{}m", distance));
+        }
+        self.label.render(renderer);
+    }
+}
+
+// The 3D scene as the window shows it, on any backend
+pub fn draw_world(renderer: &mut dyn Renderer, scene: &Scene) {
+    let run = scene.run;
+    let map_config = scene.map_config;
+    let (screen_width, screen_height) = renderer.size();
+    let view = run.camera.view();
+    let projection = run.camera.projection(
+        screen_width / screen_height,
+        scene.settings.render_distance.distance(),
+    );
+    let frustum = Frustum::from_matrix(&(projection * view));
+    let character_mesh = scene.meshes.get(scene.character_mesh);
+
+    let sun_direction = Vector3::from(map_config.lighting.sun_direction);
+    let light_space = ShadowMap::light_space_matrix(&sun_direction);
+    let mut shadow_casters: Vec<(&Mesh, Vec<Instance>)> =
+        obstacle_instances(run, &Frustum::from_matrix(&light_space))
+            .into_iter()
+            .map(|(obstacle_type, instances)| (scene.obstacle_mesh(&obstacle_type), instances))
+            .collect();
+    shadow_casters.push((
+        character_mesh,
+        vec![Instance::new(character_model(scene.character))],
+    ));
+
+    let panorama = scene.panorama.map(Handle::get);
+    renderer.begin_scene(&SceneSetup {
+        view,
+        projection,
+        eye: run.camera.eye(),
+        world_z: run.z,
+        lighting: &map_config.lighting,
+        fog: &map_config.fog,
+        background: map_config.background.as_ref(),
+        panorama: panorama.as_deref(),
+        light_space,
+        shadow_casters: &shadow_casters,
+    });
+
+    // Level segments, one instanced draw per mesh with the whole theme bound once
+    let mut platforms = Vec::new();
+    let mut walls = Vec::new();
+    for segment in run.level.segments() {
+        let segment_z = segment.position - run.z;
+        if !segment_visible(segment_z, &frustum) {
+            continue;
+        }
+        let wall_layer = Layer::Wall.index();
+        platforms.push(Instance {
+            model: math::translation(0.0, 0.0, segment_z),
+            layer: Layer::Floor.index(),
+        });
+        platforms.push(Instance {
+            model: math::translation(0.0, 5.0, segment_z),
+            layer: Layer::Ceiling.index(),
+        });
+        walls.push(Instance {
+            model: math::translation(3.0, 0.0, segment_z),
+            layer: wall_layer,
+        });
+        walls.push(Instance {
+            model: math::translation(-3.0, 0.0, segment_z),
+            layer: wall_layer,
+        });
+    }
+
+    let theme = scene.theme.textures();
+    let platform = scene.meshes.get(scene.platform_mesh);
+    renderer.draw_instances(platform, &platforms, Material::Theme(&theme));
+    let wall = scene.meshes.get(scene.wall_mesh);
+    renderer.draw_instances(wall, &walls, Material::Theme(&theme));
+    for (obstacle_type, instances) in obstacle_instances(run, &frustum) {
+        let mesh = scene.obstacle_mesh(&obstacle_type);
+        renderer.draw_instances(mesh, &instances, Material::Theme(&theme));
+    }
+
+    let skin = scene.skin.get();
+    renderer.draw_instances(
+        character_mesh,
+        &[Instance::new(character_model(scene.character))],
+        Material::Texture(&skin),
+    );
+    renderer.draw_particles(&scene.particles.instances(run.z), &skin);
+
+    let params = PostParams {
+        speed: (run.speed - START_SPEED) / (MAX_SPEED - START_SPEED),
+        grading: &map_config.grading,
+        desaturation: scene.desaturation,
+    };
+    renderer.end_scene(scene.settings, &params);
+}
+
+fn segment_visible(segment_z: f32, frustum: &Frustum) -> bool {
+    frustum.intersects_box(
+        &Point3::new(-3.0, 0.0, segment_z - 20.0),
+        &Point3::new(3.0, 5.0, segment_z + 20.0),
+    )
+}
+
+// Instances of the obstacles inside the frustum, grouped by type
+fn obstacle_instances(run: &Run, frustum: &Frustum) -> Vec<(ObstacleType, Vec<Instance>)> {
+    let mut groups: Vec<(ObstacleType, Vec<Instance>)> = Vec::new();
+    let offset = Vector3::new(0.0, 0.0, run.z);
+    for segment in run.level.segments() {
+        for obstacle in &segment.obstacles {
+            let aabb = obstacle.get_aabb();
+            if !frustum.intersects_box(&(aabb.min - offset), &(aabb.max - offset)) {
+                continue;
+            }
+            let instance = Instance {
+                model: obstacle_model(obstacle, run.z),
+                layer: Layer::obstacle(&obstacle.obstacle_type).index(),
+            };
+            match groups
+                .iter_mut()
+                .find(|(obstacle_type, _)| *obstacle_type == obstacle.obstacle_type)
+            {
+                Some((_, instances)) => instances.push(instance),
+                None => groups.push((obstacle.obstacle_type.clone(), vec![instance])),
+            }
+        }
+    }
+    groups
+}
+
+fn obstacle_model(obstacle: &Obstacle, world_z: f32) -> Matrix4<f32> {
+    math::translation(
+        obstacle.position.x,
+        obstacle.position.y,
+        obstacle.position.z - world_z,
+    )
+}
+
+fn character_model(character: &Character) -> Matrix4<f32> {
+    math::translation(character.position.x, character.position.y + 0.001, 0.0)
+        * math::scaling(1.0, character.current_height, 1.0)
+}
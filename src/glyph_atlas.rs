@@ -0,0 +1,164 @@
+use crate::resources;
+use crate::texture::Image;
+use nalgebra::Vector2;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use thiserror::Error;
+
+// TrueType/OpenType fonts tried in order for characters the bitmap font lacks
+const FALLBACK_FONTS: &str = "assets/fonts/fallback.json";
+// Pixels per text unit that glyphs are rasterized at
+pub const GLYPH_PIXELS: f32 = 48.0;
+const ATLAS_SIZE: u32 = 1024;
+// Empty pixels around each glyph, so filtering never bleeds into a neighbour
+const PADDING: u32 = 1;
+
+static ATLAS: OnceLock<Mutex<GlyphAtlas>> = OnceLock::new();
+
+#[derive(Error, Debug)]
+pub enum FontFileError {
+    #[error("Failed to open font {0}: {1}")]
+    Io(String, std::io::Error),
+    #[error("Invalid font {0}: {1}")]
+    Parse(String, &'static str),
+}
+
+// Sizes are in pixels at `GLYPH_PIXELS`
+#[derive(Clone, Copy)]
+pub struct AtlasGlyph {
+    // Texture rectangle, bottom-left and top-right
+    pub uv_min: Vector2<f32>,
+    pub uv_max: Vector2<f32>,
+    pub width: f32,
+    pub height: f32,
+    // Left edge from the pen, bottom edge from the baseline
+    pub x_min: f32,
+    pub y_min: f32,
+    pub advance: f32,
+}
+
+// Glyphs rasterized on first use into one texture, packed in rows from the bottom
+struct GlyphAtlas {
+    fonts: Vec<fontdue::Font>,
+    image: Image,
+    // `None` for characters no font has
+    glyphs: HashMap<char, Option<AtlasGlyph>>,
+    cursor: (u32, u32),
+    row_height: u32,
+    // Bumped whenever the image changes, so renderers know to upload it again
+    version: u64,
+}
+
+fn load_font(path: &str) -> Result<fontdue::Font, FontFileError> {
+    let data = resources::read(path).map_err(|e| FontFileError::Io(path.into(), e))?;
+    fontdue::Font::from_bytes(data, fontdue::FontSettings::default())
+        .map_err(|e| FontFileError::Parse(path.into(), e))
+}
+
+impl GlyphAtlas {
+    fn load() -> Self {
+        let paths: Vec<String> = match resources::read_to_string(FALLBACK_FONTS) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+                eprintln!("Invalid font list {}: {}", FALLBACK_FONTS, e);
+                Vec::new()
+            }),
+            Err(e) => {
+                eprintln!("Failed to load font list {}: {}", FALLBACK_FONTS, e);
+                Vec::new()
+            }
+        };
+        let fonts = paths
+            .iter()
+            .filter_map(|path| {
+                load_font(path)
+                    .map_err(|e| eprintln!("{}, skipping it", e))
+                    .ok()
+            })
+            .collect();
+
+        Self {
+            fonts,
+            image: Image {
+                width: ATLAS_SIZE,
+                height: ATLAS_SIZE,
+                pixels: vec![0; (ATLAS_SIZE * ATLAS_SIZE * 4) as usize],
+            },
+            glyphs: HashMap::new(),
+            cursor: (PADDING, PADDING),
+            row_height: 0,
+            version: 0,
+        }
+    }
+
+    fn glyph(&mut self, c: char) -> Option<AtlasGlyph> {
+        if let Some(glyph) = self.glyphs.get(&c) {
+            return *glyph;
+        }
+        let glyph = self.rasterize(c);
+        self.glyphs.insert(c, glyph);
+        glyph
+    }
+
+    fn rasterize(&mut self, c: char) -> Option<AtlasGlyph> {
+        let font = self
+            .fonts
+            .iter()
+            .find(|font| font.lookup_glyph_index(c) != 0)?;
+        let (metrics, coverage) = font.rasterize(c, GLYPH_PIXELS);
+        let (width, height) = (metrics.width as u32, metrics.height as u32);
+
+        // Next row when this one is full, nothing more once the texture is
+        if self.cursor.0 + width + PADDING > ATLAS_SIZE {
+            self.cursor = (PADDING, self.cursor.1 + self.row_height + PADDING);
+            self.row_height = 0;
+        }
+        if self.cursor.1 + height + PADDING > ATLAS_SIZE {
+            eprintln!("Glyph atlas is full, {:?} can't be shown", c);
+            return None;
+        }
+        let (x, y) = self.cursor;
+        self.cursor.0 += width + PADDING;
+        self.row_height = self.row_height.max(height);
+
+        // Coverage rows come top first, the image is stored bottom row first
+        for (row, line) in coverage.chunks_exact(width.max(1) as usize).enumerate() {
+            let image_row = y + height - 1 - row as u32;
+            for (column, &alpha) in line.iter().enumerate() {
+                let index = ((image_row * ATLAS_SIZE + x + column as u32) * 4) as usize;
+                self.image.pixels[index..index + 4].copy_from_slice(&[255, 255, 255, alpha]);
+            }
+        }
+        self.version += 1;
+
+        let size = ATLAS_SIZE as f32;
+        Some(AtlasGlyph {
+            uv_min: Vector2::new(x as f32 / size, y as f32 / size),
+            uv_max: Vector2::new((x + width) as f32 / size, (y + height) as f32 / size),
+            width: width as f32,
+            height: height as f32,
+            x_min: metrics.xmin as f32,
+            y_min: metrics.ymin as f32,
+            advance: metrics.advance_width,
+        })
+    }
+}
+
+fn atlas() -> &'static Mutex<GlyphAtlas> {
+    ATLAS.get_or_init(|| Mutex::new(GlyphAtlas::load()))
+}
+
+// The glyph from the first fallback font that has it, rasterized if it's new
+pub fn glyph(c: char) -> Option<AtlasGlyph> {
+    atlas().lock().unwrap().glyph(c)
+}
+
+// The atlas image and its version, `None` until a glyph was asked for
+pub fn with_image<R>(f: impl FnOnce(Option<(&Image, u64)>) -> R) -> R {
+    match ATLAS.get() {
+        Some(atlas) => {
+            let atlas = atlas.lock().unwrap();
+            f(Some((&atlas.image, atlas.version)))
+        }
+        None => f(None),
+    }
+}
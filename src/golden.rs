@@ -0,0 +1,292 @@
+// Golden-image tests: a game state rendered offscreen with the CPU rasterizer and
+// compared to a reference in `tests/golden`. Run with UPDATE_GOLDEN=1 to rewrite them
+use crate::assets::AssetManager;
+use crate::audio::AudioSystem;
+use crate::camera::{Camera, CameraMode};
+use crate::character::Character;
+use crate::game::{draw_world, Collision, Hud, Run, Scene};
+use crate::game_over::GameOver;
+use crate::map_config::MapConfig;
+use crate::map_select::{MapSelect, Maps};
+use crate::menu::{Menu, MenuAction, MessageBox};
+use crate::mesh::Mesh;
+use crate::mesh_registry::MeshRegistry;
+use crate::model::ObstacleMeshes;
+use crate::particles::ParticleSystem;
+use crate::pause::Pause;
+use crate::renderer::Renderer;
+use crate::settings::{Settings, SettingsMenu};
+use crate::skin_select::{SkinSelect, Skins};
+use crate::sky::Background;
+use crate::software_renderer::SoftwareRenderer;
+use crate::texture::{Image, TextureOptions};
+use crate::theme::Theme;
+use crate::ui::Pointer;
+use crate::{GameState, FONT};
+use std::collections::HashMap;
+
+const WIDTH: u32 = 1024;
+const HEIGHT: u32 = 768;
+// Fixed step, so a tick count always lands on the same frame
+const TICK: f32 = 1.0 / 60.0;
+// Differences allowed for floating point drift between platforms
+const CHANNEL_TOLERANCE: u8 = 8;
+const MAX_DIFFERENT_PIXELS: f32 = 0.002;
+
+// Advances a run with the given seed by `ticks` of `Run::advance`, the window's only
+// step (or until the first hit, as the character never dodges), and draws `state` with
+// the window's own scene and screens. There are no particles, their emitters are random
+fn render_frame(state: &GameState, map: &Maps, seed: u64, ticks: u32) -> Image {
+    let font = Image::load(FONT).expect("Failed to load the font");
+    let mut renderer = SoftwareRenderer::new(WIDTH, HEIGHT, font);
+
+    let map_config = MapConfig::load(map);
+    let mut meshes = MeshRegistry::new();
+    let platform_mesh = meshes.insert("platform", Mesh::platform());
+    let wall_mesh = meshes.insert("wall", Mesh::wall());
+    let obstacle_meshes = ObstacleMeshes::load(&mut meshes, &map_config.models);
+    let character_mesh = meshes.insert("character", Mesh::cube(Mesh::PLAYER_COLOR));
+    let mut assets = AssetManager::new();
+    let theme = Theme::load(&mut assets, map, &map_config.theme_texture);
+    let skin = assets.texture("assets/textures/skins/red.png", &TextureOptions::default());
+    let panorama = match &map_config.background {
+        Some(Background::Panorama { texture }) => {
+            Some(assets.texture(texture, &TextureOptions::default()))
+        }
+        _ => None,
+    };
+    assets.wait();
+    let settings = Settings::default();
+    // Emitters are random, so the frames have no particles
+    let particles = ParticleSystem::new(settings.particles);
+
+    let camera = Camera::new(map_config.camera.clone(), CameraMode::default());
+    let mut run = Run::new(seed, camera);
+    let mut character = Character::new();
+    for _ in 0..ticks {
+        if let Collision::Hit = run.advance(&mut character, TICK) {
+            break;
+        }
+    }
+    let scene = Scene {
+        run: &run,
+        character: &character,
+        map_config: &map_config,
+        meshes: &meshes,
+        platform_mesh,
+        wall_mesh,
+        obstacle_meshes: &obstacle_meshes,
+        character_mesh,
+        settings: &settings,
+        theme: &theme,
+        skin: &skin,
+        panorama: panorama.as_ref(),
+        particles: &particles,
+        desaturation: 0.0,
+    };
+
+    let unlocked_maps = unlocked(&[("campus", true), ("cave", true), ("temple", false)]);
+    let unlocked_skins = unlocked(&[
+        ("red", true),
+        ("jumper", true),
+        ("troll", false),
+        ("dirt", true),
+        ("stone", false),
+        ("diamond", false),
+        ("emerald", false),
+        ("arcane", false),
+    ]);
+    match state {
+        GameState::Menu => Menu::new().render(&mut renderer),
+        GameState::MapSelect => {
+            let mut map_select = MapSelect::new();
+            map_select.refresh(&unlocked_maps, map);
+            map_select.render(&mut renderer);
+        }
+        GameState::SkinSelect => {
+            let mut skin_select = SkinSelect::new();
+            skin_select.refresh(&unlocked_skins, &Skins::Red("red".into()));
+            skin_select.render(&mut renderer);
+        }
+        GameState::Settings => SettingsMenu::new(&Settings::default()).render(&mut renderer),
+        GameState::ShowMessage(msg) => MessageBox::new().render(msg, &mut renderer),
+        GameState::Playing => {
+            draw_world(&mut renderer, &scene);
+            Hud::new().render(&mut renderer, &run);
+        }
+        GameState::FreeCamera => draw_world(&mut renderer, &scene),
+        GameState::Paused => {
+            draw_world(&mut renderer, &scene);
+            Pause::new().render(&mut renderer);
+        }
+        GameState::GameOver => {
+            draw_world(&mut renderer, &scene);
+            GameOver::new().render(&mut renderer, run.z as i32 / 10, true);
+        }
+    }
+    renderer.image()
+}
+
+fn unlocked(names: &[(&str, bool)]) -> HashMap<String, bool> {
+    names
+        .iter()
+        .map(|&(name, unlocked)| (name.to_string(), unlocked))
+        .collect()
+}
+
+fn check(name: &str, state: GameState, map: Maps, seed: u64, ticks: u32) {
+    compare(name, &render_frame(&state, &map, seed, ticks));
+}
+
+fn compare(name: &str, actual: &Image) {
+    let path = format!("tests/golden/{}.png", name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all("tests/golden").unwrap();
+        actual.save(&path).unwrap();
+        return;
+    }
+
+    let expected = Image::load(&path)
+        .unwrap_or_else(|e| panic!("{} (run with UPDATE_GOLDEN=1 to create it)", e));
+    assert_eq!(
+        (actual.width, actual.height),
+        (expected.width, expected.height),
+        "{} has a different size",
+        path
+    );
+    let different = actual
+        .pixels
+        .chunks_exact(4)
+        .zip(expected.pixels.chunks_exact(4))
+        .filter(|(a, b)| {
+            a.iter()
+                .zip(*b)
+                .any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE)
+        })
+        .count();
+    let fraction = different as f32 / (WIDTH * HEIGHT) as f32;
+    if fraction > MAX_DIFFERENT_PIXELS {
+        let output = std::env::temp_dir().join(format!("42run-{}.png", name));
+        let output = output.to_string_lossy();
+        actual.save(&output).unwrap();
+        panic!(
+            "{} differs in {:.2}% of the pixels, the frame was written to {}",
+            path,
+            fraction * 100.0,
+            output
+        );
+    }
+}
+
+fn campus() -> Maps {
+    Maps::Campus("campus".into())
+}
+
+#[test]
+fn menu() {
+    check("menu", GameState::Menu, campus(), 0, 0);
+}
+
+#[test]
+fn menu_pressed() {
+    // A held button is drawn darker and only clicks once it's let go
+    let font = Image::load(FONT).expect("Failed to load the font");
+    let mut renderer = SoftwareRenderer::new(WIDTH, HEIGHT, font);
+    let audio = AudioSystem::silent();
+    let mut menu = Menu::new();
+    let mut pointer = Pointer {
+        position: (512.0, 424.0),
+        ..Pointer::default()
+    };
+    pointer.press();
+    let action = menu.update(&pointer, renderer.size(), &audio);
+    assert!(matches!(action, MenuAction::None));
+    menu.render(&mut renderer);
+    compare("menu_pressed", &renderer.image());
+
+    pointer.next_frame();
+    pointer.release();
+    let action = menu.update(&pointer, renderer.size(), &audio);
+    assert!(matches!(action, MenuAction::MapSelect));
+}
+
+#[test]
+fn map_select() {
+    check("map_select", GameState::MapSelect, campus(), 0, 0);
+}
+
+#[test]
+fn skin_select() {
+    check("skin_select", GameState::SkinSelect, campus(), 0, 0);
+}
+
+#[test]
+fn settings() {
+    check("settings", GameState::Settings, campus(), 0, 0);
+}
+
+#[test]
+fn message() {
+    let msg = GameState::ShowMessage("Reach 1000m in any map".into());
+    check("message", msg, campus(), 0, 0);
+}
+
+#[test]
+fn message_wrapped() {
+    let msg = "Play 15 games in cave, then reach 500m in temple without sliding";
+    check(
+        "message_wrapped",
+        GameState::ShowMessage(msg.into()),
+        campus(),
+        0,
+        0,
+    );
+}
+
+#[test]
+fn message_reused() {
+    // A kept message box lays a new message out like a fresh one
+    let font = || Image::load(FONT).expect("Failed to load the font");
+    let mut message_box = MessageBox::new();
+    let mut renderer = SoftwareRenderer::new(WIDTH, HEIGHT, font());
+    message_box.render("Reach 1000m in any map", &mut renderer);
+    let mut renderer = SoftwareRenderer::new(WIDTH, HEIGHT, font());
+    let msg = "Play 15 games in cave, then reach 500m in temple without sliding";
+    message_box.render(msg, &mut renderer);
+    compare("message_wrapped", &renderer.image());
+}
+
+#[test]
+fn playing_campus() {
+    check("playing_campus", GameState::Playing, campus(), 42, 60);
+}
+
+#[test]
+fn playing_cave() {
+    let cave = Maps::Cave("cave".into());
+    check("playing_cave", GameState::Playing, cave, 7, 90);
+}
+
+#[test]
+fn paused() {
+    check("paused", GameState::Paused, campus(), 42, 60);
+}
+
+#[test]
+fn game_over() {
+    let temple = Maps::Temple("temple".into());
+    check("game_over", GameState::GameOver, temple, 3, 10_000);
+}
+
+#[test]
+fn message_unicode() {
+    // Letters the bitmap font lacks come from the fallback TrueType font
+    let msg = "Atteins 1000m — Привет, ★ über alles!";
+    check(
+        "message_unicode",
+        GameState::ShowMessage(msg.into()),
+        campus(),
+        0,
+        0,
+    );
+}
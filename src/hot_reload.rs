@@ -0,0 +1,173 @@
+use crate::renderer::Renderer;
+use crate::resources;
+use crate::shader::Shader;
+use crate::text;
+use crate::ui::{Anchor, Label, Panel, Placement};
+use nalgebra::Vector3;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+// Polls the modification time of every file under the watched directories,
+// in each asset root, and reports paths relative to their root
+pub struct FileWatcher {
+    changes: Receiver<PathBuf>,
+}
+
+impl FileWatcher {
+    const INTERVAL: Duration = Duration::from_millis(500);
+
+    pub fn new(directories: &'static [&'static str]) -> Self {
+        let roots = resources::roots();
+        let (sender, changes) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut times = scan(roots, directories);
+            loop {
+                thread::sleep(Self::INTERVAL);
+                let current = scan(roots, directories);
+                for (path, time) in &current {
+                    // New files count too, so a missing texture can be added in place
+                    if times.get(path) != Some(time) && sender.send(path.1.clone()).is_err() {
+                        return;
+                    }
+                }
+                times = current;
+            }
+        });
+
+        Self { changes }
+    }
+
+    // Files written since the last call, each listed once
+    pub fn changes(&self) -> Vec<PathBuf> {
+        let mut changes: Vec<PathBuf> = self.changes.try_iter().collect();
+        changes.sort();
+        changes.dedup();
+        changes
+    }
+}
+
+// Modification times by root and relative path
+fn scan(roots: &[PathBuf], directories: &[&str]) -> HashMap<(usize, PathBuf), SystemTime> {
+    let mut times = HashMap::new();
+    for (index, root) in roots.iter().enumerate() {
+        for directory in directories {
+            scan_directory(root, &root.join(directory), index, &mut times);
+        }
+    }
+    times
+}
+
+fn scan_directory(
+    root: &Path,
+    directory: &Path,
+    index: usize,
+    times: &mut HashMap<(usize, PathBuf), SystemTime>,
+) {
+    let mut pending = vec![directory.to_path_buf()];
+    while let Some(directory) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&directory) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            match entry.metadata() {
+                Ok(metadata) if metadata.is_dir() => pending.push(path),
+                Ok(metadata) => {
+                    if let (Ok(modified), Ok(relative)) =
+                        (metadata.modified(), path.strip_prefix(root))
+                    {
+                        times.insert((index, relative.to_path_buf()), modified);
+                    }
+                }
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+// Recompiles the shaders built from `path`, a failed one keeps its last good program
+pub fn reload_shaders(path: &Path, shaders: Vec<&mut Shader>, errors: &mut ErrorPanel) {
+    for shader in shaders {
+        if !shader.uses(path) {
+            continue;
+        }
+        match shader.reload() {
+            Ok(()) => {
+                errors.errors.remove(&shader.name());
+            }
+            Err(e) => {
+                eprintln!("{}: {}", shader.name(), e);
+                errors.errors.insert(shader.name(), e.to_string());
+            }
+        }
+    }
+}
+
+// Reload errors shown over the game until the file is fixed
+pub struct ErrorPanel {
+    errors: BTreeMap<String, String>,
+    panel: Panel,
+    // One per shown line, unchanged lines aren't laid out again
+    lines: Vec<Label>,
+}
+
+impl ErrorPanel {
+    const TEXT_SCALE: f32 = 16.0;
+    const MARGIN: f32 = 20.0;
+
+    pub fn new() -> Self {
+        Self {
+            errors: BTreeMap::new(),
+            // Hanging from the top of the screen
+            panel: Panel::new(
+                Placement::new(Anchor::TopLeft, (Self::MARGIN, -Self::MARGIN), (0.0, 0.0)),
+                Vector3::new(0.35, 0.05, 0.05),
+            ),
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn render(&mut self, renderer: &mut dyn Renderer) {
+        if self.errors.is_empty() {
+            return;
+        }
+        let (screen_width, screen_height) = renderer.size();
+
+        let box_width = screen_width - Self::MARGIN * 2.0;
+        let text_width = (box_width - Self::MARGIN) / Self::TEXT_SCALE;
+        let max_lines = ((screen_height / 2.0) / Self::TEXT_SCALE) as usize;
+        let mut lines = Vec::new();
+        for (name, error) in &self.errors {
+            lines.push(name.as_str());
+            for line in error.trim_end_matches('\0').lines() {
+                lines.extend(text::wrap(line.trim_end(), text_width));
+            }
+        }
+        lines.truncate(max_lines);
+        let count = self.lines.len();
+        self.lines.extend((count..lines.len()).map(|i| {
+            let offset = (
+                Self::MARGIN * 1.5,
+                -Self::MARGIN - (i as f32 + 0.5) * Self::TEXT_SCALE,
+            );
+            Label::new("", Anchor::TopLeft, offset, Self::TEXT_SCALE)
+                .color(Vector3::new(1.0, 0.85, 0.85))
+        }));
+        self.lines.truncate(lines.len());
+        for (label, line) in self.lines.iter_mut().zip(&lines) {
+            label.set_text(line);
+        }
+
+        let box_height = (lines.len() as f32 + 1.0) * Self::TEXT_SCALE;
+        self.panel.placement.size = (box_width, box_height);
+        self.panel.render(renderer);
+        for label in &self.lines {
+            label.render(renderer);
+        }
+    }
+}
@@ -1,9 +1,14 @@
 use crate::character::AABB;
-use crate::mesh::Mesh;
+use crate::resources;
 use nalgebra::{Point3, Vector3};
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
 
-#[derive(Debug, Clone, PartialEq)]
+pub const PATTERNS: &str = "assets/obstacles.json";
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ObstacleType {
     Cube,
     LowBar,
@@ -13,32 +18,88 @@ pub enum ObstacleType {
 
 pub struct LevelSegment {
     pub position: f32,
-    pub platform: Mesh,
-    pub wall: Mesh,
     pub obstacles: Vec<Obstacle>,
 }
 
 pub struct Obstacle {
-    pub mesh: Mesh,
     pub position: Point3<f32>,
     pub obstacle_type: ObstacleType,
 }
 
+// What a segment can hold, one pattern is picked at random per segment
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ObstaclePatterns {
+    pub patterns: Vec<Pattern>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Pattern {
+    pub obstacles: Vec<PatternObstacle>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PatternObstacle {
+    #[serde(rename = "type")]
+    pub obstacle_type: ObstacleType,
+    // Each copy stands at one of these, picked at random
+    pub x: Vec<f32>,
+    pub y: f32,
+    // Copies, from the first number to the second
+    pub count: [u32; 2],
+}
+
+impl Default for PatternObstacle {
+    fn default() -> Self {
+        Self {
+            obstacle_type: ObstacleType::Cube,
+            x: vec![0.0],
+            y: 0.001,
+            count: [1, 1],
+        }
+    }
+}
+
+impl ObstaclePatterns {
+    pub fn load() -> Self {
+        let data = match resources::read_to_string(PATTERNS) {
+            Ok(data) => data,
+            Err(e) => {
+                eprintln!("Failed to load obstacle patterns {}: {}", PATTERNS, e);
+                return Self::default();
+            }
+        };
+        serde_json::from_str(&data).unwrap_or_else(|e| {
+            eprintln!("Invalid obstacle patterns {}: {}", PATTERNS, e);
+            Self::default()
+        })
+    }
+}
+
 pub struct LevelGenerator {
     segments: Vec<LevelSegment>,
     next_z: f32,
+    rng: StdRng,
+    patterns: ObstaclePatterns,
 }
 
 impl LevelGenerator {
     const SEGMENT_SPACING: f32 = 20.0;
     const VISIBLE_SEGMENTS: usize = 15;
     const OBSTACLE_OFFSET: f32 = 15.0;
+    // Segments closer than this are kept when the patterns change under a run
+    const RELOAD_DISTANCE: f32 = 100.0;
     pub const LANE_WIDTH: f32 = 2.0;
 
-    pub fn new() -> Self {
+    // The same seed always lays out the same obstacles
+    pub fn with_seed(seed: u64) -> Self {
         let mut generator = Self {
             segments: Vec::new(),
             next_z: 0.0,
+            rng: StdRng::seed_from_u64(seed),
+            patterns: ObstaclePatterns::load(),
         };
 
         // Generate initial segments
@@ -59,69 +120,57 @@ impl LevelGenerator {
 
         let segment = LevelSegment {
             position: self.next_z,
-            platform: Mesh::platform(),
-            wall: Mesh::wall(),
             obstacles,
         };
 
         self.segments.push(segment);
     }
 
-    fn generate_obstacles(&self, z_pos: f32) -> Vec<Obstacle> {
+    fn generate_obstacles(&mut self, z_pos: f32) -> Vec<Obstacle> {
         let mut obstacles = Vec::new();
-        let mut rng = rand::thread_rng();
-
-        let obstacle_type = match rng.gen_range(0..=3) {
-            0 => ObstacleType::Cube,
-            1 => ObstacleType::LowBar,
-            2 => ObstacleType::TallWall,
-            _ => ObstacleType::HighBar,
-        };
-
-        match obstacle_type {
-            ObstacleType::Cube => {
-                // Randomly spawn 1 or 2 cubes.
-                let num_cubes = rng.gen_range(1..=2);
-                for _ in 0..num_cubes {
-                    let lane = rng.gen_range(-1..=1);
-                    obstacles.push(Obstacle {
-                        mesh: Mesh::cube(Mesh::OBSTACLE_COLOR),
-                        position: Point3::new(
-                            lane as f32 * Self::LANE_WIDTH,
-                            0.001,
-                            z_pos + Self::OBSTACLE_OFFSET,
-                        ),
-                        obstacle_type: ObstacleType::Cube,
-                    });
-                }
-            }
-            ObstacleType::LowBar => {
-                obstacles.push(Obstacle {
-                    mesh: Mesh::wide_rectangle(),
-                    position: Point3::new(0.0, 0.001, z_pos + Self::OBSTACLE_OFFSET),
-                    obstacle_type: ObstacleType::LowBar,
-                });
-            }
-            ObstacleType::TallWall => {
-                let is_left = rng.gen_bool(0.5);
-                let x_position = if is_left { -1.0 } else { 1.0 };
-                obstacles.push(Obstacle {
-                    mesh: Mesh::tall_pillar(),
-                    position: Point3::new(x_position, 0.001, z_pos + Self::OBSTACLE_OFFSET),
-                    obstacle_type: ObstacleType::TallWall,
-                });
-            }
-            ObstacleType::HighBar => {
+        let patterns = &self.patterns.patterns;
+        if patterns.is_empty() {
+            return obstacles;
+        }
+        let rng = &mut self.rng;
+
+        // Random draws are only made for actual choices, so a fixed pattern
+        // doesn't shift the rest of the level
+        let pattern = &patterns[rng.gen_range(0..patterns.len())];
+        for obstacle in &pattern.obstacles {
+            let [min, max] = obstacle.count;
+            let count = if max > min {
+                rng.gen_range(min..=max)
+            } else {
+                min
+            };
+            for _ in 0..count {
+                let x = match obstacle.x.len() {
+                    0 => 0.0,
+                    1 => obstacle.x[0],
+                    choices => obstacle.x[rng.gen_range(0..choices)],
+                };
                 obstacles.push(Obstacle {
-                    mesh: Mesh::low_bar(),
-                    position: Point3::new(0.0, 0.8, z_pos + Self::OBSTACLE_OFFSET),
-                    obstacle_type: ObstacleType::HighBar,
+                    position: Point3::new(x, obstacle.y, z_pos + Self::OBSTACLE_OFFSET),
+                    obstacle_type: obstacle.obstacle_type.clone(),
                 });
             }
         }
         obstacles
     }
 
+    // New patterns apply from a little ahead of the player, the nearer segments
+    // stay as they are so nothing appears right in front of them
+    pub fn set_patterns(&mut self, patterns: ObstaclePatterns, world_z: f32) {
+        self.patterns = patterns;
+        let keep = world_z + Self::RELOAD_DISTANCE;
+        self.segments.retain(|segment| segment.position <= keep);
+        if let Some(last) = self.segments.last() {
+            self.next_z = last.position + Self::SEGMENT_SPACING;
+        }
+        self.update(world_z);
+    }
+
     pub fn update(&mut self, world_z: f32) {
         let generation_threshold = world_z + 1000.0;
         while self.next_z < generation_threshold {
@@ -0,0 +1,97 @@
+use crate::shader::Shader;
+use nalgebra::Vector3;
+use serde::Deserialize;
+
+pub const MAX_POINT_LIGHTS: usize = 8;
+
+#[derive(Deserialize, Clone)]
+pub struct PointLight {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    pub range: f32,
+    // Distance between copies repeated along the track, 0 keeps the light next to the player
+    #[serde(default)]
+    pub spacing: f32,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct Lighting {
+    pub ambient: [f32; 3],
+    pub sun_direction: [f32; 3],
+    pub sun_color: [f32; 3],
+    pub shininess: f32,
+    pub specular_strength: f32,
+    // How much of the ambient light a shadow takes away
+    pub shadow_strength: f32,
+    pub point_lights: Vec<PointLight>,
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Self {
+            ambient: [0.4, 0.4, 0.4],
+            sun_direction: [-0.3, -1.0, 0.4],
+            sun_color: [0.8, 0.8, 0.8],
+            shininess: 32.0,
+            specular_strength: 0.3,
+            shadow_strength: 0.4,
+            point_lights: Vec::new(),
+        }
+    }
+}
+
+impl Lighting {
+    // Point lights closer than this behind the player are skipped
+    const BEHIND_DISTANCE: f32 = 15.0;
+    const AHEAD_DISTANCE: f32 = 150.0;
+
+    pub unsafe fn apply(&self, shader: &Shader, world_z: f32, view_pos: &Vector3<f32>) {
+        shader.set("ambientColor", &Vector3::from(self.ambient));
+        shader.set(
+            "sunDirection",
+            &Vector3::from(self.sun_direction).normalize(),
+        );
+        shader.set("sunColor", &Vector3::from(self.sun_color));
+        shader.set("shininess", &self.shininess);
+        shader.set("specularStrength", &self.specular_strength);
+        shader.set("shadowStrength", &self.shadow_strength);
+        shader.set("viewPos", view_pos);
+
+        let lights = self.visible_point_lights(world_z);
+        shader.set("pointLightCount", &(lights.len() as i32));
+        for (i, (position, light)) in lights.iter().enumerate() {
+            shader.set(&format!("pointLights[{}].position", i), position);
+            shader.set(
+                &format!("pointLights[{}].color", i),
+                &Vector3::from(light.color),
+            );
+            shader.set(&format!("pointLights[{}].range", i), &light.range);
+        }
+    }
+
+    // Point light positions relative to the player, nearest first
+    pub fn visible_point_lights(&self, world_z: f32) -> Vec<(Vector3<f32>, &PointLight)> {
+        let mut lights = Vec::new();
+        for light in &self.point_lights {
+            let base = Vector3::from(light.position);
+            if light.spacing <= 0.0 {
+                lights.push((base, light));
+                continue;
+            }
+
+            let mut z = base.z - world_z.rem_euclid(light.spacing);
+            while z < -Self::BEHIND_DISTANCE {
+                z += light.spacing;
+            }
+            while z < Self::AHEAD_DISTANCE {
+                lights.push((Vector3::new(base.x, base.y, z), light));
+                z += light.spacing;
+            }
+        }
+
+        lights.sort_by(|a, b| a.0.z.abs().total_cmp(&b.0.z.abs()));
+        lights.truncate(MAX_POINT_LIGHTS);
+        lights
+    }
+}
@@ -1,65 +1,120 @@
+mod assets;
 mod audio;
+mod camera;
 mod character;
 mod controls;
+mod framebuffer;
 mod game;
 mod game_over;
+mod glyph_atlas;
+#[cfg(test)]
+mod golden;
+mod hot_reload;
 mod level;
+mod lighting;
+mod map_config;
 mod map_select;
 mod math;
 mod menu;
 mod mesh;
+mod mesh_builder;
+mod mesh_registry;
+mod model;
+mod particles;
 mod pause;
+mod postprocess;
+mod preprocessor;
+mod renderer;
+mod resources;
 mod save_data;
+mod settings;
 mod shader;
+mod shadow;
 mod skin_select;
+mod sky;
+mod software_renderer;
+mod text;
 mod texture;
+mod theme;
+#[cfg(unix)]
+mod tty;
+mod ui;
 
+use crate::assets::{AssetManager, Handle};
 use crate::audio::AudioSystem;
+use crate::camera::{Camera, CameraMode};
 use crate::controls::handle_keys;
-use crate::game::{new_game, play};
+use crate::framebuffer::Framebuffer;
+use crate::game::{draw_world, new_game, play, Hud, Run, Scene};
 use crate::game_over::{GameOver, GameOverAction};
-use crate::level::LevelGenerator;
+use crate::hot_reload::{reload_shaders, ErrorPanel, FileWatcher};
+use crate::level::{LevelGenerator, ObstaclePatterns, PATTERNS};
+use crate::map_config::MapConfig;
 use crate::map_select::{MapAction, MapSelect, Maps};
-use crate::menu::{render_message, Menu, MenuAction};
+use crate::menu::{Menu, MenuAction, MessageBox};
 use crate::mesh::Mesh;
+use crate::mesh_registry::{MeshHandle, MeshRegistry};
+use crate::model::{load_skin_config, ObstacleMeshes};
+use crate::particles::{Effects, Emitter, ParticleSystem};
 use crate::pause::{Pause, PauseAction};
+use crate::renderer::{GlRenderer, Renderer};
 use crate::save_data::{extract_save_data, load_progress, save_progress};
+use crate::settings::{Settings, SettingsAction, SettingsMenu};
 use crate::skin_select::{SkinAction, SkinSelect, Skins};
-use crate::texture::Texture;
-use glfw::{Action, Context, MouseButton, WindowEvent};
+use crate::sky::Background;
+use crate::software_renderer::SoftwareRenderer;
+use crate::texture::{Image, Texture, TextureOptions};
+use crate::theme::Theme;
+use crate::ui::Pointer;
+use glfw::{Action, Context, Key, MouseButton, WindowEvent};
+use nalgebra::Vector3;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 #[derive(Clone)]
 enum GameState {
     Menu,
     MapSelect,
     SkinSelect,
+    Settings,
     ShowMessage(String),
     Playing,
+    FreeCamera,
     GameOver,
     Paused,
 }
 
 struct WorldState {
-    speed: f32,
-    z: f32,
+    run: Run,
     last_frame_time: f64,
     screen_width: f32,
     screen_height: f32,
-    mouse_x: f32,
-    mouse_y: f32,
-    mouse_clicked: bool,
+    pointer: Pointer,
     menu: Menu,
     pause: Pause,
     game_over: GameOver,
-    level: LevelGenerator,
+    map_config: MapConfig,
     pause_start_time: f64,
     total_pause_time: f64,
     record: bool,
     current_skin: Skins,
     current_map: Maps,
     current_music: Option<String>,
-    textures: HashMap<String, Texture>,
+    assets: AssetManager,
+    skin: Handle<Texture>,
+    panorama: Option<Handle<Texture>>,
+    theme: Theme,
+    meshes: MeshRegistry,
+    platform_mesh: MeshHandle,
+    wall_mesh: MeshHandle,
+    obstacle_meshes: ObstacleMeshes,
+    character_mesh: MeshHandle,
+    settings: Settings,
+    desaturation: f32,
+    particles: ParticleSystem,
+    effects: Effects,
+    slide_sparks: Emitter,
+    ambient_particles: Vec<Emitter>,
     audio: AudioSystem,
     unlocked_maps: HashMap<String, bool>,
     unlocked_skins: HashMap<String, bool>,
@@ -67,42 +122,55 @@ struct WorldState {
 }
 
 impl WorldState {
+    fn scene<'a>(&'a self, character: &'a character::Character) -> Scene<'a> {
+        Scene {
+            run: &self.run,
+            character,
+            map_config: &self.map_config,
+            meshes: &self.meshes,
+            platform_mesh: self.platform_mesh,
+            wall_mesh: self.wall_mesh,
+            obstacle_meshes: &self.obstacle_meshes,
+            character_mesh: self.character_mesh,
+            settings: &self.settings,
+            theme: &self.theme,
+            skin: &self.skin,
+            panorama: self.panorama.as_ref(),
+            particles: &self.particles,
+            desaturation: self.desaturation,
+        }
+    }
+
     fn change_map(&mut self) {
-        let map_path = match &self.current_map {
-            Maps::Campus(path) | Maps::Cave(path) | Maps::Temple(path) => {
-                format!("assets/textures/maps/{}", path)
+        self.map_config = MapConfig::load(&self.current_map);
+        self.obstacle_meshes = ObstacleMeshes::load(&mut self.meshes, &self.map_config.models);
+        self.load_background();
+        self.load_ambient_particles();
+        self.theme = Theme::load(
+            &mut self.assets,
+            &self.current_map,
+            &self.map_config.theme_texture,
+        );
+    }
+
+    fn load_background(&mut self) {
+        self.panorama = match &self.map_config.background {
+            Some(Background::Panorama { texture }) => {
+                Some(self.assets.texture(texture, &TextureOptions::default()))
             }
-            Maps::None => String::from("assets/textures/maps/campus.png"),
+            _ => None,
         };
+    }
 
-        self.textures.insert(
-            "floor".into(),
-            Texture::new(&format!("{}/floor.png", map_path)),
-        );
-        self.textures.insert(
-            "wall".into(),
-            Texture::new(&format!("{}/wall.png", map_path)),
-        );
-        self.textures.insert(
-            "ceiling".into(),
-            Texture::new(&format!("{}/ceiling.png", map_path)),
-        );
-        self.textures.insert(
-            "cube".into(),
-            Texture::new(&format!("{}/cube.png", map_path)),
-        );
-        self.textures.insert(
-            "lowBar".into(),
-            Texture::new(&format!("{}/lowBar.png", map_path)),
-        );
-        self.textures.insert(
-            "tallWall".into(),
-            Texture::new(&format!("{}/tallWall.png", map_path)),
-        );
-        self.textures.insert(
-            "highBar".into(),
-            Texture::new(&format!("{}/highBar.png", map_path)),
-        );
+    fn load_ambient_particles(&mut self) {
+        self.particles.clear();
+        self.ambient_particles = self
+            .map_config
+            .particles
+            .iter()
+            .cloned()
+            .map(Emitter::new)
+            .collect();
     }
 
     fn change_skin(&mut self) {
@@ -118,14 +186,184 @@ impl WorldState {
             Skins::None => String::from("assets/textures/skins/red.png"),
         };
 
-        self.textures
-            .insert("skin".into(), Texture::new(&format!("{}.png", skin_path)));
+        self.skin = self
+            .assets
+            .texture(&format!("{}.png", skin_path), &TextureOptions::default());
+        self.load_character_model();
+    }
+
+    fn load_character_model(&mut self) {
+        let skin_config = match &self.current_skin {
+            Skins::None => Default::default(),
+            Skins::Red(name)
+            | Skins::Troll(name)
+            | Skins::Dirt(name)
+            | Skins::Stone(name)
+            | Skins::Diamond(name)
+            | Skins::Emerald(name)
+            | Skins::Arcane(name)
+            | Skins::Jumper(name) => load_skin_config(name),
+        };
+        let mesh = model::load_or_else(skin_config.model.as_ref(), || {
+            Mesh::cube(Mesh::PLAYER_COLOR)
+        });
+        self.meshes.insert("character", mesh);
+    }
+
+    // Applies an asset or data file edited while the game runs
+    fn reload_file(&mut self, path: &Path) {
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        match extension {
+            Some("png") | Some("wav") => self.assets.reload(path),
+            Some("json") if path == Path::new(&MapConfig::path(&self.current_map)) => {
+                self.change_map()
+            }
+            Some("json") if path == Path::new("assets/particles.json") => {
+                self.effects = Effects::load();
+                self.slide_sparks = Emitter::new(self.effects.slide_sparks.clone());
+            }
+            Some("json") if path == Path::new("assets/skins.json") => self.load_character_model(),
+            Some("json") if path == Path::new(PATTERNS) => {
+                let z = self.run.z;
+                self.run.level.set_patterns(ObstaclePatterns::load(), z);
+            }
+            Some("obj") | Some("mtl") | Some("gltf") | Some("glb") | Some("bin") => {
+                self.obstacle_meshes =
+                    ObstacleMeshes::load(&mut self.meshes, &self.map_config.models);
+                self.load_character_model();
+            }
+            _ => {}
+        }
+    }
+}
+
+const FONT: &str = "assets/fonts/MinecraftRegular.png";
+
+// The GL renderer, or the software one when it was asked for on the command line
+struct Renderers {
+    gl: GlRenderer,
+    software: Option<SoftwareRenderer>,
+}
+
+impl Renderers {
+    fn active(&mut self) -> &mut dyn Renderer {
+        match &mut self.software {
+            Some(software) => software,
+            None => &mut self.gl,
+        }
+    }
+
+    fn resize(&mut self, screen_width: f32, screen_height: f32) {
+        self.gl.resize(screen_width, screen_height);
+        if let Some(software) = &mut self.software {
+            software.resize(screen_width as u32, screen_height as u32);
+        }
+    }
+
+    fn scene(&mut self, world: &WorldState, character: &character::Character) {
+        draw_world(self.active(), &world.scene(character));
+    }
+
+    // Copies the software frame to the window
+    fn present(&self, screen_width: f32, screen_height: f32) {
+        if let Some(software) = &self.software {
+            Framebuffer::present(&software.image(), screen_width, screen_height);
+        }
+    }
+
+    fn capture(&self, screen_width: f32, screen_height: f32) -> Image {
+        match &self.software {
+            Some(software) => software.image(),
+            None => Framebuffer::read_default(screen_width, screen_height),
+        }
+    }
+}
+
+fn save_screenshot(image: &Image) {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis());
+    let path = format!("screenshots/42run-{}.png", millis);
+    if let Err(e) = std::fs::create_dir_all("screenshots") {
+        eprintln!("Failed to create the screenshots directory: {}", e);
+        return;
+    }
+    match image.save(&path) {
+        Ok(()) => eprintln!("Saved {}", path),
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+fn software_font() -> Image {
+    Image::load(FONT).unwrap_or_else(|e| {
+        eprintln!("{}, using a placeholder", e);
+        Image::placeholder()
+    })
+}
+
+struct Args {
+    // Directory holding `assets/` and `shaders/`
+    assets: Option<PathBuf>,
+    debug_uniforms: bool,
+    // Watches shaders/ and assets/ and applies edits while the game runs
+    hot_reload: bool,
+    // Draws everything on the CPU and shows the result, to check the software renderer
+    software: bool,
+    // Plays in the terminal instead of a window
+    tty: bool,
+    // Level seed for --tty, random when missing
+    seed: Option<u64>,
+}
+
+impl Args {
+    fn parse() -> Self {
+        let mut args = Args {
+            assets: None,
+            debug_uniforms: false,
+            hot_reload: cfg!(debug_assertions),
+            software: false,
+            tty: false,
+            seed: None,
+        };
+        let mut iter = std::env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--debug-uniforms" => args.debug_uniforms = true,
+                "--hot-reload" => args.hot_reload = true,
+                "--no-hot-reload" => args.hot_reload = false,
+                "--software" => args.software = true,
+                "--tty" => args.tty = true,
+                "--seed" => match iter.next().map(|seed| seed.parse()) {
+                    Some(Ok(seed)) => args.seed = Some(seed),
+                    _ => eprintln!("--seed needs a number"),
+                },
+                "--assets" => match iter.next() {
+                    Some(directory) => args.assets = Some(directory.into()),
+                    None => eprintln!("--assets needs a directory"),
+                },
+                _ => match arg.strip_prefix("--assets=") {
+                    Some(directory) => args.assets = Some(directory.into()),
+                    None => eprintln!("Ignoring unknown argument {}", arg),
+                },
+            }
+        }
+        args
     }
 }
 
 fn main() {
     const SCREEN_WIDTH: f32 = 1024.0;
     const SCREEN_HEIGHT: f32 = 768.0;
+    let args = Args::parse();
+    resources::init(args.assets);
+    shader::debug_uniforms(args.debug_uniforms);
+    if args.tty {
+        #[cfg(unix)]
+        tty::run(args.seed);
+        #[cfg(not(unix))]
+        eprintln!("--tty is only available on Unix terminals");
+        return;
+    }
     let mut glfw = glfw::init(glfw::fail_on_errors).unwrap();
     let (mut window, events) = glfw
         .create_window(
@@ -149,82 +387,73 @@ fn main() {
         gl::DepthFunc(gl::LESS);
     }
 
-    let game_shader = shader::Shader::new("shaders/vertex/game.glsl", "shaders/fragment/game.glsl")
-        .expect("Failed to load shaders");
-    let ui_shader = shader::Shader::new("shaders/vertex/ui.glsl", "shaders/fragment/ui.glsl")
-        .expect("Failed to load UI shaders");
-    let text_shader = shader::Shader::new("shaders/vertex/text.glsl", "shaders/fragment/text.glsl")
-        .expect("Failed to load text shaders");
-
-    let mut textures = HashMap::new();
-    textures.insert(
-        "font".into(),
-        Texture::new("assets/fonts/MinecraftRegular.png"),
-    );
-    textures.insert("skin".into(), Texture::new("assets/textures/skins/red.png"));
-    textures.insert(
-        "floor".into(),
-        Texture::new("assets/textures/maps/campus/floor.png"),
-    );
-    textures.insert(
-        "wall".into(),
-        Texture::new("assets/textures/maps/campus/wall.png"),
-    );
-    textures.insert(
-        "ceiling".into(),
-        Texture::new("assets/textures/maps/campus/ceiling.png"),
-    );
-    textures.insert(
-        "cube".into(),
-        Texture::new("assets/textures/maps/campus/cube.png"),
-    );
-    textures.insert(
-        "lowBar".into(),
-        Texture::new("assets/textures/maps/campus/lowBar.png"),
-    );
-    textures.insert(
-        "tallWall".into(),
-        Texture::new("assets/textures/maps/campus/tallWall.png"),
-    );
-    textures.insert(
-        "highBar".into(),
-        Texture::new("assets/textures/maps/campus/highBar.png"),
-    );
+    let mut assets = AssetManager::new();
+    let font = assets.texture(FONT, &TextureOptions::ui());
+    let mut renderers = Renderers {
+        gl: GlRenderer::new(font, SCREEN_WIDTH, SCREEN_HEIGHT).expect("Failed to load UI shaders"),
+        software: args.software.then(|| {
+            SoftwareRenderer::new(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32, software_font())
+        }),
+    };
+    let skin = assets.texture("assets/textures/skins/red.png", &TextureOptions::default());
 
     let mut audio = AudioSystem::new();
-    audio.load_sound("jump", "assets/sounds/jump.wav");
-    audio.load_sound("slide", "assets/sounds/slide.wav");
-    audio.load_sound("collision1", "assets/sounds/diarrhea.wav");
-    audio.load_sound("collision2", "assets/sounds/explosion.wav");
-    audio.load_sound("button1", "assets/sounds/button1.wav");
-    audio.load_sound("button2", "assets/sounds/button2.wav");
-    audio.music_volume(0.4);
-    audio.sound_volume(0.4);
-
-    let character_mesh = Mesh::cube(Mesh::PLAYER_COLOR);
+    audio.load_sound("jump", "assets/sounds/jump.wav", &mut assets);
+    audio.load_sound("slide", "assets/sounds/slide.wav", &mut assets);
+    audio.load_sound("collision1", "assets/sounds/diarrhea.wav", &mut assets);
+    audio.load_sound("collision2", "assets/sounds/explosion.wav", &mut assets);
+    audio.load_sound("button1", "assets/sounds/button1.wav", &mut assets);
+    audio.load_sound("button2", "assets/sounds/button2.wav", &mut assets);
+
     let mut game_state = GameState::Menu;
     let mut previous_state = GameState::Menu;
     let mut character = character::Character::new();
+    let map_config = MapConfig::load(&Maps::Campus("campus".into()));
+    let mut meshes = MeshRegistry::new();
+    let platform_mesh = meshes.insert("platform", Mesh::platform());
+    let wall_mesh = meshes.insert("wall", Mesh::wall());
+    let obstacle_meshes = ObstacleMeshes::load(&mut meshes, &map_config.models);
+    let theme = Theme::load(
+        &mut assets,
+        &Maps::Campus("campus".into()),
+        &map_config.theme_texture,
+    );
+    let character_mesh = meshes.insert("character", Mesh::cube(Mesh::PLAYER_COLOR));
+    let effects = Effects::load();
     let mut world = WorldState {
-        speed: 20.0,
-        z: 0.0,
+        run: Run::new(
+            rand::random(),
+            Camera::new(map_config.camera.clone(), CameraMode::default()),
+        ),
         last_frame_time: glfw.get_time(),
         screen_width: SCREEN_WIDTH,
         screen_height: SCREEN_HEIGHT,
-        mouse_x: 0.0,
-        mouse_y: 0.0,
-        mouse_clicked: false,
-        menu: Menu::new(SCREEN_WIDTH, SCREEN_HEIGHT),
-        pause: Pause::new(SCREEN_WIDTH, SCREEN_HEIGHT),
-        game_over: GameOver::new(SCREEN_WIDTH, SCREEN_HEIGHT),
-        level: level::LevelGenerator::new(),
+        pointer: Pointer::default(),
+        menu: Menu::new(),
+        pause: Pause::new(),
+        game_over: GameOver::new(),
+        map_config,
         pause_start_time: 0.0,
         total_pause_time: 0.0,
         record: false,
         current_skin: Skins::Red("red".into()),
         current_map: Maps::Campus("campus".into()),
         current_music: None,
-        textures,
+        assets,
+        skin,
+        panorama: None,
+        theme,
+        meshes,
+        platform_mesh,
+        wall_mesh,
+        obstacle_meshes,
+        character_mesh,
+        settings: Settings::default(),
+        desaturation: 0.0,
+        particles: ParticleSystem::new(Settings::default().particles),
+        slide_sparks: Emitter::new(effects.slide_sparks.clone()),
+        effects,
+        ambient_particles: Vec::new(),
         audio,
         unlocked_maps: HashMap::from([
             ("campus".into(), true),
@@ -250,10 +479,15 @@ fn main() {
             ("jumps".into(), 0),
         ]),
     };
+    world.load_background();
+    world.load_ambient_particles();
+    world.load_character_model();
     if let Ok(save_data) = load_progress() {
         world.unlocked_maps = save_data.unlocked_maps;
         world.unlocked_skins = save_data.unlocked_skins;
         world.quest_progress = save_data.quest_progress;
+        world.run.camera.set_mode(save_data.camera_mode);
+        world.settings = save_data.settings;
         if world.current_map != save_data.current_map {
             world.current_map = save_data.current_map;
             world.change_map();
@@ -263,28 +497,50 @@ fn main() {
             world.change_skin();
         }
     }
-    let mut map_select: MapSelect;
-    let mut skin_select: SkinSelect;
+    renderers.gl.set_shadows(world.settings.shadows);
+    world.particles.set_quality(world.settings.particles);
+    // Only startup waits, later loads stream in while the menus run
+    world.assets.wait();
+    world.audio.music_volume(world.settings.music_volume);
+    world.audio.sound_volume(world.settings.sound_volume);
+    // Screens keep their meshes between frames, their widgets are anchored to the
+    // screen edges so resizing needs no rebuild
+    let mut map_select = MapSelect::new();
+    let mut skin_select = SkinSelect::new();
+    let mut settings_menu = SettingsMenu::new(&world.settings);
+    let mut message_box = MessageBox::new();
+    let mut hud = Hud::new();
+    let watcher = args
+        .hot_reload
+        .then(|| FileWatcher::new(&["shaders", "assets"]));
+    let mut reload_errors = ErrorPanel::new();
 
     while !window.should_close() {
+        let changes = watcher.as_ref().map(FileWatcher::changes);
+        for path in changes.into_iter().flatten() {
+            reload_shaders(&path, renderers.gl.shaders_mut(), &mut reload_errors);
+            world.reload_file(&path);
+        }
+        world.assets.update();
+        let mut screenshot = false;
+        world.pointer.next_frame();
         for (_, event) in glfw::flush_messages(&events) {
             match event {
+                WindowEvent::Key(Key::F12, _, Action::Press, _) => screenshot = true,
                 WindowEvent::FramebufferSize(width, height) => {
                     unsafe { gl::Viewport(0, 0, width, height) };
                     world.screen_width = width as f32;
                     world.screen_height = height as f32;
-                    world.menu = Menu::new(world.screen_width, world.screen_height);
-                    world.pause = Pause::new(world.screen_width, world.screen_height);
-                    world.game_over = GameOver::new(world.screen_width, world.screen_height);
+                    renderers.resize(world.screen_width, world.screen_height);
                 }
                 WindowEvent::CursorPos(x, y) => {
-                    world.mouse_x = x as f32;
-                    world.mouse_y = world.screen_height - y as f32;
+                    world.pointer.position = (x as f32, world.screen_height - y as f32);
                 }
-                WindowEvent::MouseButton(button, action, _) => {
-                    if button == MouseButton::Left && action == Action::Press {
-                        world.mouse_clicked = true;
-                    }
+                WindowEvent::MouseButton(MouseButton::Left, Action::Press, _) => {
+                    world.pointer.press()
+                }
+                WindowEvent::MouseButton(MouseButton::Left, Action::Release, _) => {
+                    world.pointer.release()
                 }
                 _ => handle_keys(
                     &mut window,
@@ -298,117 +554,79 @@ fn main() {
             }
         }
 
+        let screen = (world.screen_width, world.screen_height);
         match game_state {
             GameState::Menu => {
                 if world.current_music != Some("menu".to_string()) {
                     world.audio.play_music("assets/music/megalovania.wav");
                     world.current_music = Some("menu".to_string());
                 }
-                unsafe {
-                    world
-                        .menu
-                        .render(&ui_shader, &text_shader, &world.textures["font"]);
-                }
-                if world.mouse_clicked {
-                    match world
-                        .menu
-                        .handle_click(world.mouse_x, world.mouse_y, &world.audio)
-                    {
-                        MenuAction::Play => {
-                            new_game(&mut game_state, &mut character, &mut world, &glfw)
-                        }
-                        MenuAction::MapSelect => game_state = GameState::MapSelect,
-                        MenuAction::SkinSelect => game_state = GameState::SkinSelect,
-                        MenuAction::Quit => window.set_should_close(true),
-                        MenuAction::None => {}
+                match world.menu.update(&world.pointer, screen, &world.audio) {
+                    MenuAction::Play => {
+                        new_game(&mut game_state, &mut character, &mut world, &glfw)
                     }
-                    world.mouse_clicked = false;
+                    MenuAction::MapSelect => game_state = GameState::MapSelect,
+                    MenuAction::SkinSelect => game_state = GameState::SkinSelect,
+                    MenuAction::Settings => game_state = GameState::Settings,
+                    MenuAction::Quit => window.set_should_close(true),
+                    MenuAction::None => {}
                 }
+                world.menu.render(renderers.active());
             }
             GameState::MapSelect => {
-                map_select = MapSelect::new(
-                    world.screen_width,
-                    world.screen_height,
-                    &world.unlocked_maps,
-                );
-                unsafe {
-                    map_select.render(
-                        &ui_shader,
-                        &text_shader,
-                        &world.current_map,
-                        &world.textures["font"],
-                    );
-                }
-                if world.mouse_clicked {
-                    match map_select.handle_click(
-                        world.mouse_x,
-                        world.mouse_y,
-                        &world.audio,
-                        &world.current_map,
-                    ) {
-                        MapAction::SelectMap(map) => {
-                            world.current_map = map;
-                            world.change_map();
-                        }
-                        MapAction::ShowMessage(msg) => {
-                            previous_state = GameState::MapSelect;
-                            game_state = GameState::ShowMessage(msg);
-                        }
-                        MapAction::Back => game_state = GameState::Menu,
-                        _ => {}
+                map_select.refresh(&world.unlocked_maps, &world.current_map);
+                match map_select.update(&world.pointer, screen, &world.audio) {
+                    MapAction::SelectMap(map) => {
+                        world.current_map = map;
+                        world.change_map();
+                    }
+                    MapAction::ShowMessage(msg) => {
+                        previous_state = GameState::MapSelect;
+                        game_state = GameState::ShowMessage(msg);
                     }
-                    world.mouse_clicked = false;
+                    MapAction::Back => game_state = GameState::Menu,
+                    MapAction::None => {}
                 }
+                map_select.render(renderers.active());
             }
             GameState::SkinSelect => {
-                skin_select = SkinSelect::new(
-                    world.screen_width,
-                    world.screen_height,
-                    &world.unlocked_skins,
-                );
-                unsafe {
-                    skin_select.render(
-                        &ui_shader,
-                        &text_shader,
-                        &world.current_skin,
-                        &world.textures["font"],
-                    );
+                skin_select.refresh(&world.unlocked_skins, &world.current_skin);
+                match skin_select.update(&world.pointer, screen, &world.audio) {
+                    SkinAction::SelectSkin(skin) => {
+                        world.current_skin = skin;
+                        world.change_skin();
+                    }
+                    SkinAction::ShowMessage(msg) => {
+                        previous_state = GameState::SkinSelect;
+                        game_state = GameState::ShowMessage(msg);
+                    }
+                    SkinAction::Back => game_state = GameState::Menu,
+                    SkinAction::None => {}
                 }
-                if world.mouse_clicked {
-                    match skin_select.handle_click(
-                        world.mouse_x,
-                        world.mouse_y,
-                        &world.audio,
-                        &world.current_skin,
-                    ) {
-                        SkinAction::SelectSkin(skin) => {
-                            world.current_skin = skin;
-                            world.change_skin();
-                        }
-                        SkinAction::ShowMessage(msg) => {
-                            previous_state = GameState::SkinSelect;
-                            game_state = GameState::ShowMessage(msg);
-                        }
-                        SkinAction::Back => game_state = GameState::Menu,
-                        _ => {}
+                skin_select.render(renderers.active());
+            }
+            GameState::Settings => {
+                let settings = &mut world.settings;
+                match settings_menu.update(&world.pointer, screen, &world.audio, settings) {
+                    SettingsAction::Shadows => renderers.gl.set_shadows(world.settings.shadows),
+                    SettingsAction::Particles => {
+                        world.particles.set_quality(world.settings.particles)
+                    }
+                    SettingsAction::Volume => {
+                        world.audio.music_volume(world.settings.music_volume);
+                        world.audio.sound_volume(world.settings.sound_volume);
                     }
-                    world.mouse_clicked = false;
+                    SettingsAction::Back => game_state = GameState::Menu,
+                    SettingsAction::None => {}
                 }
+                settings_menu.render(renderers.active());
             }
-            GameState::ShowMessage(ref msg) => unsafe {
-                render_message(
-                    &msg,
-                    &ui_shader,
-                    &text_shader,
-                    world.screen_width,
-                    world.screen_height,
-                    &world.textures["font"],
-                );
-                if world.mouse_clicked {
+            GameState::ShowMessage(ref msg) => {
+                message_box.render(msg, renderers.active());
+                if world.pointer.pressed {
                     game_state = previous_state.clone();
-                    world.mouse_clicked = false;
                 }
-            },
+            }
             GameState::Playing => {
                 let map_music = match world.current_map {
                     Maps::Campus(_) => "assets/music/death_by_glamour.wav",
@@ -426,65 +644,81 @@ fn main() {
                 let adjusted_time: f64 = current_time - world.total_pause_time;
                 let delta_time: f32 = (adjusted_time - world.last_frame_time) as f32;
                 world.last_frame_time = adjusted_time;
-                character.update(delta_time);
-                play(
-                    &mut world,
-                    &mut character,
-                    &mut game_state,
-                    &game_shader,
-                    &character_mesh,
-                    &text_shader,
-                    delta_time,
+                play(&mut world, &mut character, &mut game_state, delta_time);
+                renderers.scene(&world, &character);
+                hud.render(renderers.active(), &world.run);
+            }
+            GameState::FreeCamera => {
+                let current_time: f64 = glfw.get_time();
+                let delta_time: f32 = (current_time - world.pause_start_time) as f32;
+                world.pause_start_time = current_time;
+                world.total_pause_time += delta_time as f64;
+
+                let axis = |positive: Key, negative: Key| {
+                    let mut value = 0.0;
+                    if window.get_key(positive) == Action::Press {
+                        value += 1.0;
+                    }
+                    if window.get_key(negative) == Action::Press {
+                        value -= 1.0;
+                    }
+                    value
+                };
+                let movement = Vector3::new(
+                    axis(Key::D, Key::A),
+                    axis(Key::Space, Key::LeftShift),
+                    axis(Key::W, Key::S),
                 );
+                world
+                    .run
+                    .camera
+                    .fly(movement, world.pointer.position, delta_time);
+                renderers.scene(&world, &character);
             }
             GameState::Paused => {
                 world.audio.pause_music();
-                unsafe {
-                    world
-                        .pause
-                        .render(&ui_shader, &text_shader, &world.textures["font"]);
-                }
-                if world.mouse_clicked {
-                    match world
-                        .pause
-                        .handle_click(world.mouse_x, world.mouse_y, &world.audio)
-                    {
-                        PauseAction::Resume => {
-                            world.audio.resume_music();
-                            game_state = GameState::Playing;
-                            world.total_pause_time += glfw.get_time() - world.pause_start_time;
-                        }
-                        PauseAction::Quit => game_state = GameState::Menu,
-                        PauseAction::None => {}
+                match world.pause.update(&world.pointer, screen, &world.audio) {
+                    PauseAction::Resume => {
+                        world.audio.resume_music();
+                        game_state = GameState::Playing;
+                        world.total_pause_time += glfw.get_time() - world.pause_start_time;
                     }
-                    world.mouse_clicked = false;
+                    PauseAction::Quit => game_state = GameState::Menu,
+                    PauseAction::None => {}
                 }
+                world.pause.render(renderers.active());
             }
             GameState::GameOver => {
-                unsafe {
-                    world.game_over.render(
-                        &ui_shader,
-                        &text_shader,
-                        *world.quest_progress.get("highScore").unwrap_or(&0),
-                        world.record,
-                        &world.textures["font"],
-                    );
-                }
-                if world.mouse_clicked {
-                    match world
-                        .game_over
-                        .handle_click(world.mouse_x, world.mouse_y, &world.audio)
-                    {
-                        GameOverAction::NewGame => {
-                            new_game(&mut game_state, &mut character, &mut world, &glfw)
-                        }
-                        GameOverAction::Quit => game_state = GameState::Menu,
-                        GameOverAction::None => {}
+                let current_time: f64 = glfw.get_time();
+                let adjusted_time: f64 = current_time - world.total_pause_time;
+                let delta_time: f32 = (adjusted_time - world.last_frame_time) as f32;
+                world.last_frame_time = adjusted_time;
+                world
+                    .run
+                    .camera
+                    .update(&character, world.run.speed, delta_time);
+                world.particles.update(delta_time);
+                world.desaturation = (world.desaturation + delta_time).min(1.0);
+                renderers.scene(&world, &character);
+                match world.game_over.update(&world.pointer, screen, &world.audio) {
+                    GameOverAction::NewGame => {
+                        new_game(&mut game_state, &mut character, &mut world, &glfw)
                     }
-                    world.mouse_clicked = false;
+                    GameOverAction::Quit => game_state = GameState::Menu,
+                    GameOverAction::None => {}
                 }
+                world.game_over.render(
+                    renderers.active(),
+                    *world.quest_progress.get("highScore").unwrap_or(&0),
+                    world.record,
+                );
             }
         }
+        reload_errors.render(renderers.active());
+        renderers.present(world.screen_width, world.screen_height);
+        if screenshot {
+            save_screenshot(&renderers.capture(world.screen_width, world.screen_height));
+        }
         window.swap_buffers();
         glfw.poll_events();
     }
@@ -0,0 +1,51 @@
+use crate::camera::CameraSettings;
+use crate::lighting::Lighting;
+use crate::map_select::Maps;
+use crate::model::ObstacleModels;
+use crate::particles::EmitterConfig;
+use crate::postprocess::ColorGrading;
+use crate::resources;
+use crate::sky::{Background, Fog};
+use crate::texture::TextureOptions;
+use serde::Deserialize;
+
+#[derive(Deserialize, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct MapConfig {
+    pub camera: CameraSettings,
+    pub lighting: Lighting,
+    pub fog: Fog,
+    pub background: Option<Background>,
+    pub particles: Vec<EmitterConfig>,
+    pub grading: ColorGrading,
+    pub models: ObstacleModels,
+    // Wrap and filter of the theme, one texture array so the same for every layer
+    pub theme_texture: TextureOptions,
+}
+
+impl MapConfig {
+    pub fn path(map: &Maps) -> String {
+        match map {
+            Maps::Campus(name) | Maps::Cave(name) | Maps::Temple(name) => {
+                format!("assets/maps/{}.json", name)
+            }
+            Maps::None => String::from("assets/maps/campus.json"),
+        }
+    }
+
+    pub fn load(map: &Maps) -> Self {
+        let path = Self::path(map);
+
+        let data = match resources::read_to_string(&path) {
+            Ok(data) => data,
+            Err(e) => {
+                eprintln!("Failed to load map config {}: {}", path, e);
+                return Self::default();
+            }
+        };
+        serde_json::from_str(&data).unwrap_or_else(|e| {
+            eprintln!("Invalid map config {}: {}", path, e);
+            Self::default()
+        })
+    }
+}
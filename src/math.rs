@@ -1,4 +1,4 @@
-use nalgebra::{Matrix4, Vector3};
+use nalgebra::{Matrix4, Point3, Vector3, Vector4};
 
 pub fn translation(x: f32, y: f32, z: f32) -> Matrix4<f32> {
     Matrix4::new(
@@ -80,3 +80,36 @@ pub fn look_at(eye: Vector3<f32>, target: Vector3<f32>, up: Vector3<f32>) -> Mat
     let translation = translation(-eye.x, -eye.y, -eye.z);
     orientation * translation
 }
+
+// Clip volume of a view-projection matrix, as six inward-facing planes
+pub struct Frustum {
+    planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    pub fn from_matrix(view_projection: &Matrix4<f32>) -> Self {
+        let row = |i: usize| view_projection.row(i).transpose();
+        let planes = [
+            row(3) + row(0),
+            row(3) - row(0),
+            row(3) + row(1),
+            row(3) - row(1),
+            row(3) + row(2),
+            row(3) - row(2),
+        ]
+        .map(|plane| plane / plane.xyz().norm());
+        Self { planes }
+    }
+
+    pub fn intersects_box(&self, min: &Point3<f32>, max: &Point3<f32>) -> bool {
+        self.planes.iter().all(|plane| {
+            // Corner furthest along the plane normal
+            let corner = Vector3::new(
+                if plane.x >= 0.0 { max.x } else { min.x },
+                if plane.y >= 0.0 { max.y } else { min.y },
+                if plane.z >= 0.0 { max.z } else { min.z },
+            );
+            plane.xyz().dot(&corner) + plane.w >= 0.0
+        })
+    }
+}
@@ -1,5 +1,7 @@
+use crate::mesh_builder::MeshBuilder;
 use gl::types::*;
-use nalgebra::{Vector2, Vector3};
+use nalgebra::{Matrix4, Vector2, Vector3};
+use std::cell::{Cell, OnceCell};
 
 #[repr(C)]
 #[derive(Clone)]
@@ -7,30 +9,53 @@ pub struct Vertex {
     pub position: Vector3<f32>,
     pub color: Vector3<f32>,
     pub tex_coords: Vector2<f32>,
+    pub normal: Vector3<f32>,
+}
+
+#[repr(C)]
+pub struct Instance {
+    pub model: Matrix4<f32>,
+    // Texture array layer, ignored by meshes drawn with a plain texture
+    pub layer: f32,
+}
+
+impl Instance {
+    pub fn new(model: Matrix4<f32>) -> Self {
+        Self { model, layer: 0.0 }
+    }
 }
 
 pub struct Mesh {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    // Uploaded on the first draw, so meshes can be built and rasterized without a GL context
+    buffers: OnceCell<Buffers>,
+    pub indices_count: i32,
+}
+
+struct Buffers {
     vao: GLuint,
     vbo: GLuint,
     ebo: GLuint,
-    pub indices_count: i32,
+    // Per-instance data, created on the first instanced draw
+    instance_vbo: Cell<GLuint>,
 }
 
-impl Drop for Mesh {
+impl Drop for Buffers {
     fn drop(&mut self) {
         unsafe {
             gl::DeleteVertexArrays(1, &self.vao);
             gl::DeleteBuffers(1, &self.vbo);
             gl::DeleteBuffers(1, &self.ebo);
+            if self.instance_vbo.get() != 0 {
+                gl::DeleteBuffers(1, &self.instance_vbo.get());
+            }
         }
     }
 }
 
-impl Mesh {
-    pub const PLAYER_COLOR: Vector3<f32> = Vector3::new(1.0, 0.0, 0.0); // Red
-    pub const OBSTACLE_COLOR: Vector3<f32> = Vector3::new(1.0, 1.0, 0.0); // Yellow
-
-    pub fn new(vertices: &[Vertex], indices: &[u32]) -> Self {
+impl Buffers {
+    fn upload(vertices: &[Vertex], indices: &[u32]) -> Self {
         let mut vao = 0;
         let mut vbo = 0;
         let mut ebo = 0;
@@ -46,7 +71,7 @@ impl Mesh {
             gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
             gl::BufferData(
                 gl::ARRAY_BUFFER,
-                (vertices.len() * std::mem::size_of::<Vertex>()) as isize,
+                std::mem::size_of_val(vertices) as isize,
                 vertices.as_ptr() as *const _,
                 gl::STATIC_DRAW,
             );
@@ -55,11 +80,10 @@ impl Mesh {
             gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
             gl::BufferData(
                 gl::ELEMENT_ARRAY_BUFFER,
-                (indices.len() * std::mem::size_of::<u32>()) as isize,
+                std::mem::size_of_val(indices) as isize,
                 indices.as_ptr() as *const _,
                 gl::STATIC_DRAW,
             );
-
             // Position (location = 0)
             gl::EnableVertexAttribArray(0);
             gl::VertexAttribPointer(
@@ -93,48 +117,99 @@ impl Mesh {
                 (6 * std::mem::size_of::<f32>()) as *const _,
             );
 
+            // Normal (location = 3)
+            gl::EnableVertexAttribArray(3);
+            gl::VertexAttribPointer(
+                3,
+                3,
+                gl::FLOAT,
+                gl::FALSE,
+                std::mem::size_of::<Vertex>() as GLsizei,
+                (8 * std::mem::size_of::<f32>()) as *const _,
+            );
+
             gl::BindVertexArray(0);
         }
 
-        Mesh {
+        Buffers {
             vao,
             vbo,
             ebo,
+            instance_vbo: Cell::new(0),
+        }
+    }
+
+    // Refills the same buffers, for meshes whose content changes while they're shown
+    fn update(&self, vertices: &[Vertex], indices: &[u32]) {
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(vertices) as isize,
+                vertices.as_ptr() as *const _,
+                gl::DYNAMIC_DRAW,
+            );
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.ebo);
+            gl::BufferData(
+                gl::ELEMENT_ARRAY_BUFFER,
+                std::mem::size_of_val(indices) as isize,
+                indices.as_ptr() as *const _,
+                gl::DYNAMIC_DRAW,
+            );
+            gl::BindVertexArray(0);
+        }
+    }
+}
+
+impl Mesh {
+    pub const PLAYER_COLOR: Vector3<f32> = Vector3::new(1.0, 0.0, 0.0); // Red
+    pub const OBSTACLE_COLOR: Vector3<f32> = Vector3::new(1.0, 1.0, 0.0); // Yellow
+
+    pub fn new(vertices: &[Vertex], indices: &[u32]) -> Self {
+        Mesh {
+            vertices: vertices.to_vec(),
+            indices: indices.to_vec(),
+            buffers: OnceCell::new(),
             indices_count: indices.len() as i32,
         }
     }
 
-    pub fn platform() -> Self {
-        let vertices = vec![
-            Vertex {
-                position: Vector3::new(-3.0, 0.0, -20.0),
-                color: Vector3::zeros(),
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 0.0, -20.0),
-                color: Vector3::zeros(),
-                tex_coords: Vector2::new(1.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 0.0, 20.0),
-                color: Vector3::zeros(),
-                tex_coords: Vector2::new(1.0, 4.0),
-            },
-            Vertex {
-                position: Vector3::new(-3.0, 0.0, 20.0),
-                color: Vector3::zeros(),
-                tex_coords: Vector2::new(0.0, 4.0),
-            },
-        ];
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
 
-        let indices = vec![0, 1, 2, 2, 3, 0];
-        Mesh::new(&vertices, &indices)
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    // Replaces the content, reusing the buffers if the mesh was already drawn
+    pub fn set(&mut self, vertices: &[Vertex], indices: &[u32]) {
+        self.vertices.clear();
+        self.vertices.extend_from_slice(vertices);
+        self.indices.clear();
+        self.indices.extend_from_slice(indices);
+        self.indices_count = indices.len() as i32;
+        if let Some(buffers) = self.buffers.get() {
+            buffers.update(vertices, indices);
+        }
+    }
+
+    fn buffers(&self) -> &Buffers {
+        self.buffers
+            .get_or_init(|| Buffers::upload(&self.vertices, &self.indices))
+    }
+
+    pub fn platform() -> Self {
+        MeshBuilder::new()
+            .tile_size(6.0, 10.0)
+            .plane(Vector3::zeros(), 6.0, 40.0)
+            .build()
     }
 
     pub fn draw(&self) {
         unsafe {
-            gl::BindVertexArray(self.vao);
+            gl::BindVertexArray(self.buffers().vao);
             gl::DrawElements(
                 gl::TRIANGLES,
                 self.indices_count,
@@ -145,578 +220,101 @@ impl Mesh {
         }
     }
 
+    // Draws one copy per instance, with the model matrix at locations 4 to 7 and the layer at 8
+    pub fn draw_instanced(&self, instances: &[Instance]) {
+        if instances.is_empty() {
+            return;
+        }
+
+        let buffers = self.buffers();
+        unsafe {
+            gl::BindVertexArray(buffers.vao);
+            if buffers.instance_vbo.get() == 0 {
+                let mut instance_vbo = 0;
+                gl::GenBuffers(1, &mut instance_vbo);
+                gl::BindBuffer(gl::ARRAY_BUFFER, instance_vbo);
+                let stride = std::mem::size_of::<Instance>() as GLsizei;
+                for column in 0..4 {
+                    gl::EnableVertexAttribArray(4 + column);
+                    gl::VertexAttribPointer(
+                        4 + column,
+                        4,
+                        gl::FLOAT,
+                        gl::FALSE,
+                        stride,
+                        (column as usize * 4 * std::mem::size_of::<f32>()) as *const _,
+                    );
+                    gl::VertexAttribDivisor(4 + column, 1);
+                }
+                gl::EnableVertexAttribArray(8);
+                gl::VertexAttribPointer(
+                    8,
+                    1,
+                    gl::FLOAT,
+                    gl::FALSE,
+                    stride,
+                    std::mem::size_of::<Matrix4<f32>>() as *const _,
+                );
+                gl::VertexAttribDivisor(8, 1);
+                buffers.instance_vbo.set(instance_vbo);
+            } else {
+                gl::BindBuffer(gl::ARRAY_BUFFER, buffers.instance_vbo.get());
+            }
+
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(instances) as isize,
+                instances.as_ptr() as *const _,
+                gl::STREAM_DRAW,
+            );
+            gl::DrawElementsInstanced(
+                gl::TRIANGLES,
+                self.indices_count,
+                gl::UNSIGNED_INT,
+                std::ptr::null(),
+                instances.len() as GLsizei,
+            );
+            gl::BindVertexArray(0);
+        }
+    }
+
     pub fn cube(color: Vector3<f32>) -> Self {
-        let vertices = vec![
-            // Front face
-            Vertex {
-                position: Vector3::new(-0.5, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(0.5, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(0.5, 1.0, 0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(-0.5, 1.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-            // Back face
-            Vertex {
-                position: Vector3::new(-0.5, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(0.5, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(0.5, 1.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(-0.5, 1.0, -0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 1.0),
-            },
-            // Left face
-            Vertex {
-                position: Vector3::new(-0.5, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(-0.5, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(-0.5, 1.0, 0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(-0.5, 1.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-            // Right face
-            Vertex {
-                position: Vector3::new(0.5, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(0.5, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(0.5, 1.0, -0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(0.5, 1.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-            // Top face
-            Vertex {
-                position: Vector3::new(-0.5, 1.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(0.5, 1.0, 0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(0.5, 1.0, -0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(-0.5, 1.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-            // Bottom face
-            Vertex {
-                position: Vector3::new(-0.5, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(0.5, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(0.5, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(-0.5, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-        ];
-        let indices = vec![
-            0, 1, 2, 2, 3, 0, 4, 5, 6, 6, 7, 4, 8, 9, 10, 10, 11, 8, 12, 13, 14, 14, 15, 12, 16,
-            17, 18, 18, 19, 16, 20, 21, 22, 22, 23, 20,
-        ];
-        Mesh::new(&vertices, &indices)
+        MeshBuilder::new()
+            .color(color)
+            .cuboid(Vector3::zeros(), Vector3::new(1.0, 1.0, 1.0))
+            .build()
     }
 
     pub fn wide_rectangle() -> Self {
-        let color = Vector3::new(1.0, 0.5, 0.0); // Orange color
-        let vertices = vec![
-            // Front face
-            Vertex {
-                position: Vector3::new(-3.0, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(6.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 1.0, 0.5),
-                color,
-                tex_coords: Vector2::new(6.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(-3.0, 1.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-            // Back face
-            Vertex {
-                position: Vector3::new(-3.0, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(6.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 1.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(-3.0, 1.0, -0.5),
-                color,
-                tex_coords: Vector2::new(6.0, 1.0),
-            },
-            // Left face
-            Vertex {
-                position: Vector3::new(-3.0, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(-3.0, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(-3.0, 1.0, 0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(-3.0, 1.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-            // Right face
-            Vertex {
-                position: Vector3::new(3.0, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 1.0, -0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 1.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-            // Top face
-            Vertex {
-                position: Vector3::new(-3.0, 1.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 1.0, 0.5),
-                color,
-                tex_coords: Vector2::new(6.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 1.0, -0.5),
-                color,
-                tex_coords: Vector2::new(6.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(-3.0, 1.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-            // Bottom face
-            Vertex {
-                position: Vector3::new(-3.0, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(6.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(6.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(-3.0, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-        ];
-        let indices = vec![
-            0, 1, 2, 2, 3, 0, 4, 5, 6, 6, 7, 4, 8, 9, 10, 10, 11, 8, 12, 13, 14, 14, 15, 12, 16,
-            17, 18, 18, 19, 16, 20, 21, 22, 22, 23, 20,
-        ];
-        Mesh::new(&vertices, &indices)
+        MeshBuilder::new()
+            .color(Vector3::new(1.0, 0.5, 0.0)) // Orange color
+            .cuboid(Vector3::zeros(), Vector3::new(6.0, 1.0, 1.0))
+            .build()
     }
 
     pub fn tall_pillar() -> Self {
-        let color = Vector3::new(0.2, 0.2, 0.8); // Blue color
-        let vertices = vec![
-            // Front face
-            Vertex {
-                position: Vector3::new(-2.0, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(2.0, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(4.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(2.0, 2.0, 0.5),
-                color,
-                tex_coords: Vector2::new(4.0, 2.0),
-            },
-            Vertex {
-                position: Vector3::new(-2.0, 2.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 2.0),
-            },
-            // Back face
-            Vertex {
-                position: Vector3::new(-2.0, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(4.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(2.0, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(2.0, 2.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 2.0),
-            },
-            Vertex {
-                position: Vector3::new(-2.0, 2.0, -0.5),
-                color,
-                tex_coords: Vector2::new(4.0, 2.0),
-            },
-            // Left face
-            Vertex {
-                position: Vector3::new(-2.0, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(-2.0, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(-2.0, 2.0, 0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 2.0),
-            },
-            Vertex {
-                position: Vector3::new(-2.0, 2.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 2.0),
-            },
-            // Right face
-            Vertex {
-                position: Vector3::new(2.0, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(2.0, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(2.0, 2.0, -0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 2.0),
-            },
-            Vertex {
-                position: Vector3::new(2.0, 2.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 2.0),
-            },
-            // Top face
-            Vertex {
-                position: Vector3::new(-2.0, 2.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(2.0, 2.0, 0.5),
-                color,
-                tex_coords: Vector2::new(4.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(2.0, 2.0, -0.5),
-                color,
-                tex_coords: Vector2::new(4.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(-2.0, 2.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-            // Bottom face
-            Vertex {
-                position: Vector3::new(-2.0, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(2.0, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(4.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(2.0, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(4.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(-2.0, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-        ];
-        let indices = vec![
-            0, 1, 2, 2, 3, 0, 4, 5, 6, 6, 7, 4, 8, 9, 10, 10, 11, 8, 12, 13, 14, 14, 15, 12, 16,
-            17, 18, 18, 19, 16, 20, 21, 22, 22, 23, 20,
-        ];
-        Mesh::new(&vertices, &indices)
+        MeshBuilder::new()
+            .color(Vector3::new(0.2, 0.2, 0.8)) // Blue color
+            .cuboid(Vector3::zeros(), Vector3::new(4.0, 2.0, 1.0))
+            .build()
     }
 
     pub fn low_bar() -> Self {
-        let color = Vector3::new(1.0, 0.5, 0.0); // Orange color
-        let vertices = vec![
-            // Front face
-            Vertex {
-                position: Vector3::new(-3.0, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(6.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 1.0, 0.5),
-                color,
-                tex_coords: Vector2::new(6.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(-3.0, 1.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-            // Back face
-            Vertex {
-                position: Vector3::new(-3.0, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(6.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 1.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(-3.0, 1.0, -0.5),
-                color,
-                tex_coords: Vector2::new(6.0, 1.0),
-            },
-            // Left face
-            Vertex {
-                position: Vector3::new(-3.0, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(-3.0, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(-3.0, 1.0, 0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(-3.0, 1.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-            // Right face
-            Vertex {
-                position: Vector3::new(3.0, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 1.0, -0.5),
-                color,
-                tex_coords: Vector2::new(1.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 1.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-            // Top face
-            Vertex {
-                position: Vector3::new(-3.0, 1.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 1.0, 0.5),
-                color,
-                tex_coords: Vector2::new(6.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 1.0, -0.5),
-                color,
-                tex_coords: Vector2::new(6.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(-3.0, 1.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-            // Bottom face
-            Vertex {
-                position: Vector3::new(-3.0, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 0.0, -0.5),
-                color,
-                tex_coords: Vector2::new(6.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(3.0, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(6.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(-3.0, 0.0, 0.5),
-                color,
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-        ];
-        let indices = vec![
-            0, 1, 2, 2, 3, 0, 4, 5, 6, 6, 7, 4, 8, 9, 10, 10, 11, 8, 12, 13, 14, 14, 15, 12, 16,
-            17, 18, 18, 19, 16, 20, 21, 22, 22, 23, 20,
-        ];
-        Mesh::new(&vertices, &indices)
+        MeshBuilder::new()
+            .color(Vector3::new(1.0, 0.5, 0.0)) // Orange color
+            .cuboid(Vector3::zeros(), Vector3::new(6.0, 1.0, 1.0))
+            .build()
     }
 
     pub fn wall() -> Self {
-        let vertices = vec![
-            Vertex {
-                position: Vector3::new(0.0, 0.0, -20.0),
-                color: Vector3::zeros(),
-                tex_coords: Vector2::new(0.0, 0.0),
-            },
-            Vertex {
-                position: Vector3::new(0.0, 5.0, -20.0),
-                color: Vector3::zeros(),
-                tex_coords: Vector2::new(0.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(0.0, 5.0, 20.0),
-                color: Vector3::zeros(),
-                tex_coords: Vector2::new(4.0, 1.0),
-            },
-            Vertex {
-                position: Vector3::new(0.0, 0.0, 20.0),
-                color: Vector3::zeros(),
-                tex_coords: Vector2::new(4.0, 0.0),
-            },
-        ];
-        let indices = vec![0, 1, 2, 2, 3, 0];
-        Mesh::new(&vertices, &indices)
+        MeshBuilder::new()
+            .tile_size(10.0, 5.0)
+            .quad(
+                Vector3::new(0.0, 0.0, -20.0),
+                Vector3::new(0.0, 0.0, 40.0),
+                Vector3::new(0.0, 5.0, 0.0),
+            )
+            .build()
     }
 
     pub fn quad_2d() -> Self {
@@ -725,82 +323,28 @@ impl Mesh {
                 position: Vector3::new(0.0, 0.0, 0.0),
                 color: Vector3::zeros(),
                 tex_coords: Vector2::zeros(),
+                normal: Vector3::new(0.0, 0.0, 1.0),
             },
             Vertex {
                 position: Vector3::new(1.0, 0.0, 0.0),
                 color: Vector3::zeros(),
                 tex_coords: Vector2::zeros(),
+                normal: Vector3::new(0.0, 0.0, 1.0),
             },
             Vertex {
                 position: Vector3::new(1.0, 1.0, 0.0),
                 color: Vector3::zeros(),
                 tex_coords: Vector2::zeros(),
+                normal: Vector3::new(0.0, 0.0, 1.0),
             },
             Vertex {
                 position: Vector3::new(0.0, 1.0, 0.0),
                 color: Vector3::zeros(),
                 tex_coords: Vector2::zeros(),
+                normal: Vector3::new(0.0, 0.0, 1.0),
             },
         ];
         let indices = vec![0, 1, 2, 2, 3, 0];
         Mesh::new(&vertices, &indices)
     }
-
-    pub fn text(text: &str) -> Mesh {
-        let mut vertices = Vec::new();
-        let mut indices = Vec::new();
-        let char_width = 1.0 / 16.0;
-        let char_height = 1.0 / 16.0;
-        let scale = 1.0;
-        let mut x_offset = 0.0;
-        let mut quad_count = 0;
-
-        for c in text.chars() {
-            if c == ' ' {
-                x_offset += scale * 0.8 * 0.6;
-                continue;
-            }
-
-            let ascii = c as u32;
-            let index = ascii.checked_sub(32).unwrap_or(0);
-            let grid_x = (index % 16) as f32;
-            let grid_y = (index / 16) as f32;
-            let grid_y_effective = 15.0 - grid_y;
-
-            let u = grid_x * char_width;
-            let v = grid_y_effective * char_height;
-            let u_right = u + char_width;
-            let v_top = v + char_height;
-
-            vertices.extend_from_slice(&[
-                Vertex {
-                    position: Vector3::new(x_offset, 0.0, 0.0),
-                    color: Vector3::zeros(),
-                    tex_coords: Vector2::new(u, v),
-                },
-                Vertex {
-                    position: Vector3::new(x_offset + scale, 0.0, 0.0),
-                    color: Vector3::zeros(),
-                    tex_coords: Vector2::new(u_right, v),
-                },
-                Vertex {
-                    position: Vector3::new(x_offset + scale, scale, 0.0),
-                    color: Vector3::zeros(),
-                    tex_coords: Vector2::new(u_right, v_top),
-                },
-                Vertex {
-                    position: Vector3::new(x_offset, scale, 0.0),
-                    color: Vector3::zeros(),
-                    tex_coords: Vector2::new(u, v_top),
-                },
-            ]);
-
-            let base = (quad_count * 4) as u32;
-            indices.extend_from_slice(&[base, base + 1, base + 2, base + 2, base + 3, base]);
-            quad_count += 1;
-            x_offset += scale * 0.8;
-        }
-
-        Mesh::new(&vertices, &indices)
-    }
 }
@@ -0,0 +1,213 @@
+use crate::mesh::{Mesh, Vertex};
+use nalgebra::{Vector2, Vector3};
+use std::f32::consts::TAU;
+
+pub struct MeshBuilder {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    color: Vector3<f32>,
+    // World units covered by one repeat of the texture
+    tile_size: Vector2<f32>,
+}
+
+impl MeshBuilder {
+    pub fn new() -> Self {
+        Self {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            color: Vector3::zeros(),
+            tile_size: Vector2::new(1.0, 1.0),
+        }
+    }
+
+    pub fn color(mut self, color: Vector3<f32>) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn tile_size(mut self, u: f32, v: f32) -> Self {
+        self.tile_size = Vector2::new(u, v);
+        self
+    }
+
+    // Parallelogram from one corner and its two edges, facing u_edge x v_edge
+    pub fn quad(
+        mut self,
+        corner: Vector3<f32>,
+        u_edge: Vector3<f32>,
+        v_edge: Vector3<f32>,
+    ) -> Self {
+        let (u, v) = (u_edge.norm(), v_edge.norm());
+        self.face(
+            &[
+                corner,
+                corner + u_edge,
+                corner + u_edge + v_edge,
+                corner + v_edge,
+            ],
+            &[
+                Vector2::new(0.0, 0.0),
+                Vector2::new(u, 0.0),
+                Vector2::new(u, v),
+                Vector2::new(0.0, v),
+            ],
+        );
+        self
+    }
+
+    // Horizontal plane facing up, centred on `center`
+    pub fn plane(self, center: Vector3<f32>, width: f32, length: f32) -> Self {
+        self.quad(
+            center + Vector3::new(width / 2.0, 0.0, -length / 2.0),
+            Vector3::new(-width, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, length),
+        )
+    }
+
+    // Box standing on `base`, the centre of its bottom face
+    pub fn cuboid(self, base: Vector3<f32>, size: Vector3<f32>) -> Self {
+        let (x, y, z) = (size.x / 2.0, size.y, size.z / 2.0);
+        let width = Vector3::new(size.x, 0.0, 0.0);
+        let height = Vector3::new(0.0, size.y, 0.0);
+        let depth = Vector3::new(0.0, 0.0, size.z);
+
+        self
+            // Front, back, left, right
+            .quad(base + Vector3::new(-x, 0.0, z), width, height)
+            .quad(base + Vector3::new(x, 0.0, -z), -width, height)
+            .quad(base + Vector3::new(-x, 0.0, -z), depth, height)
+            .quad(base + Vector3::new(x, 0.0, z), -depth, height)
+            // Top, bottom
+            .quad(base + Vector3::new(-x, y, z), width, -depth)
+            .quad(base + Vector3::new(-x, 0.0, -z), width, depth)
+    }
+
+    // Upright cylinder standing on `base`, with smooth sides and flat caps
+    pub fn cylinder(mut self, base: Vector3<f32>, radius: f32, height: f32, segments: u32) -> Self {
+        let segments = segments.max(3);
+        let circumference = TAU * radius;
+        let ring = |i: u32| {
+            let angle = i as f32 / segments as f32 * TAU;
+            Vector3::new(angle.cos(), 0.0, -angle.sin())
+        };
+
+        // Sides, with the seam vertices duplicated so the texture wraps once
+        let start = self.vertices.len() as u32;
+        for i in 0..=segments {
+            let normal = ring(i);
+            let u = circumference * i as f32 / segments as f32;
+            for v in [0.0, height] {
+                self.push_vertex(
+                    base + normal * radius + Vector3::new(0.0, v, 0.0),
+                    Vector2::new(u, v),
+                    normal,
+                );
+            }
+        }
+        for i in 0..segments {
+            let bottom = start + i * 2;
+            self.indices.extend([
+                bottom,
+                bottom + 2,
+                bottom + 3,
+                bottom + 3,
+                bottom + 1,
+                bottom,
+            ]);
+        }
+
+        // Caps, mapped from above
+        for (y, normal) in [(height, Vector3::y()), (0.0, -Vector3::y())] {
+            let center = self.vertices.len() as u32;
+            self.push_vertex(
+                base + Vector3::new(0.0, y, 0.0),
+                Vector2::new(radius, radius),
+                normal,
+            );
+            for i in 0..segments {
+                let offset = ring(i) * radius;
+                self.push_vertex(
+                    base + offset + Vector3::new(0.0, y, 0.0),
+                    Vector2::new(offset.x + radius, offset.z + radius),
+                    normal,
+                );
+                let (a, b) = (center + 1 + i, center + 1 + (i + 1) % segments);
+                if normal.y > 0.0 {
+                    self.indices.extend([center, a, b]);
+                } else {
+                    self.indices.extend([center, b, a]);
+                }
+            }
+        }
+        self
+    }
+
+    // Wedge standing on `base`, rising from the front edge to `size.y` at the back
+    pub fn ramp(mut self, base: Vector3<f32>, size: Vector3<f32>) -> Self {
+        let (x, y, z) = (size.x / 2.0, size.y, size.z / 2.0);
+        let width = Vector3::new(size.x, 0.0, 0.0);
+
+        for side in [-x, x] {
+            let front = base + Vector3::new(side, 0.0, -z);
+            let back = base + Vector3::new(side, 0.0, z);
+            let top = base + Vector3::new(side, y, z);
+            let uvs = [
+                Vector2::new(0.0, 0.0),
+                Vector2::new(size.z, 0.0),
+                Vector2::new(size.z, size.y),
+            ];
+            if side < 0.0 {
+                self.face(&[front, back, top], &uvs);
+            } else {
+                self.face(&[front, top, back], &[uvs[0], uvs[2], uvs[1]]);
+            }
+        }
+
+        self
+            // Slope, back, bottom
+            .quad(
+                base + Vector3::new(x, 0.0, -z),
+                -width,
+                Vector3::new(0.0, size.y, size.z),
+            )
+            .quad(
+                base + Vector3::new(-x, 0.0, z),
+                width,
+                Vector3::new(0.0, y, 0.0),
+            )
+            .quad(
+                base + Vector3::new(-x, 0.0, -z),
+                width,
+                Vector3::new(0.0, 0.0, size.z),
+            )
+    }
+
+    pub fn build(self) -> Mesh {
+        Mesh::new(&self.vertices, &self.indices)
+    }
+
+    // Flat convex polygon, uvs in world units
+    fn face(&mut self, positions: &[Vector3<f32>], uvs: &[Vector2<f32>]) {
+        let normal = (positions[1] - positions[0])
+            .cross(&(positions[2] - positions[0]))
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(Vector3::y);
+
+        let start = self.vertices.len() as u32;
+        for (position, uv) in positions.iter().zip(uvs) {
+            self.push_vertex(*position, *uv, normal);
+        }
+        for i in 1..positions.len() as u32 - 1 {
+            self.indices.extend([start, start + i, start + i + 1]);
+        }
+    }
+
+    fn push_vertex(&mut self, position: Vector3<f32>, uv: Vector2<f32>, normal: Vector3<f32>) {
+        self.vertices.push(Vertex {
+            position,
+            color: self.color,
+            tex_coords: uv.component_div(&self.tile_size),
+            normal,
+        });
+    }
+}
@@ -0,0 +1,40 @@
+use crate::mesh::Mesh;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MeshHandle(usize);
+
+// Every geometry is uploaded once and shared by handle
+pub struct MeshRegistry {
+    meshes: Vec<Mesh>,
+    names: HashMap<String, MeshHandle>,
+}
+
+impl MeshRegistry {
+    pub fn new() -> Self {
+        Self {
+            meshes: Vec::new(),
+            names: HashMap::new(),
+        }
+    }
+
+    // Replacing a mesh keeps its handle, so holders see the new geometry
+    pub fn insert(&mut self, name: &str, mesh: Mesh) -> MeshHandle {
+        match self.names.get(name) {
+            Some(&handle) => {
+                self.meshes[handle.0] = mesh;
+                handle
+            }
+            None => {
+                let handle = MeshHandle(self.meshes.len());
+                self.meshes.push(mesh);
+                self.names.insert(name.into(), handle);
+                handle
+            }
+        }
+    }
+
+    pub fn get(&self, handle: MeshHandle) -> &Mesh {
+        &self.meshes[handle.0]
+    }
+}
@@ -0,0 +1,287 @@
+use crate::level::ObstacleType;
+use crate::mesh::{Mesh, Vertex};
+use crate::mesh_builder::MeshBuilder;
+use crate::mesh_registry::{MeshHandle, MeshRegistry};
+use crate::resources;
+use nalgebra::{Vector2, Vector3};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ModelError {
+    #[error("Failed to load OBJ model: {0}")]
+    Obj(#[from] tobj::LoadError),
+    #[error("Failed to load glTF model: {0}")]
+    Gltf(#[from] gltf::Error),
+    #[error("Unsupported model format: {0}")]
+    UnsupportedFormat(String),
+    #[error("Model has no triangles: {0}")]
+    Empty(String),
+}
+
+// Models use the same units as the built-in meshes: origin at the bottom
+// centre, one unit per lane-sized block. They reference neighbouring files,
+// so unlike other assets they are only read from disk
+pub fn load(path: &str) -> Result<Mesh, ModelError> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+    let file = resources::find(path).unwrap_or_else(|| path.into());
+    let (vertices, indices) = match extension.as_deref() {
+        Some("obj") => load_obj(&file)?,
+        Some("gltf") | Some("glb") => load_gltf(&file)?,
+        _ => return Err(ModelError::UnsupportedFormat(path.into())),
+    };
+
+    if indices.is_empty() {
+        return Err(ModelError::Empty(path.into()));
+    }
+    Ok(Mesh::new(&vertices, &indices))
+}
+
+// A model file, or a shape generated from its dimensions
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+pub enum ModelSource {
+    File(String),
+    Shape(Shape),
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(tag = "shape", rename_all = "snake_case")]
+pub enum Shape {
+    Cuboid {
+        size: [f32; 3],
+    },
+    Cylinder {
+        radius: f32,
+        height: f32,
+        #[serde(default = "default_segments")]
+        segments: u32,
+    },
+    Ramp {
+        size: [f32; 3],
+    },
+}
+
+fn default_segments() -> u32 {
+    16
+}
+
+impl Shape {
+    pub fn build(&self) -> Mesh {
+        let builder = MeshBuilder::new();
+        match *self {
+            Shape::Cuboid { size } => builder.cuboid(Vector3::zeros(), Vector3::from(size)),
+            Shape::Cylinder {
+                radius,
+                height,
+                segments,
+            } => builder.cylinder(Vector3::zeros(), radius, height, segments),
+            Shape::Ramp { size } => builder.ramp(Vector3::zeros(), Vector3::from(size)),
+        }
+        .build()
+    }
+}
+
+// Loads the declared model, otherwise builds the built-in mesh
+pub fn load_or_else(source: Option<&ModelSource>, fallback: impl FnOnce() -> Mesh) -> Mesh {
+    match source {
+        Some(ModelSource::File(path)) => load(path).unwrap_or_else(|e| {
+            eprintln!("{}, using the built-in mesh", e);
+            fallback()
+        }),
+        Some(ModelSource::Shape(shape)) => shape.build(),
+        None => fallback(),
+    }
+}
+
+fn load_obj(path: &Path) -> Result<(Vec<Vertex>, Vec<u32>), ModelError> {
+    let options = tobj::LoadOptions {
+        triangulate: true,
+        single_index: true,
+        ..Default::default()
+    };
+    let (models, _) = tobj::load_obj(path, &options)?;
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for model in models {
+        let mesh = model.mesh;
+        let base = vertices.len() as u32;
+        let start = indices.len();
+        for i in 0..mesh.positions.len() / 3 {
+            let tex_coords = if mesh.texcoords.len() >= (i + 1) * 2 {
+                Vector2::new(mesh.texcoords[i * 2], mesh.texcoords[i * 2 + 1])
+            } else {
+                Vector2::zeros()
+            };
+            let normal = if mesh.normals.len() >= (i + 1) * 3 {
+                Vector3::from_column_slice(&mesh.normals[i * 3..i * 3 + 3])
+            } else {
+                Vector3::zeros()
+            };
+            vertices.push(Vertex {
+                position: Vector3::from_column_slice(&mesh.positions[i * 3..i * 3 + 3]),
+                color: Vector3::new(1.0, 1.0, 1.0),
+                tex_coords,
+                normal,
+            });
+        }
+        indices.extend(mesh.indices.iter().map(|index| base + index));
+
+        if mesh.normals.is_empty() {
+            compute_normals(&mut vertices[base as usize..], &indices[start..], base);
+        }
+    }
+    Ok((vertices, indices))
+}
+
+fn load_gltf(path: &Path) -> Result<(Vec<Vertex>, Vec<u32>), ModelError> {
+    let (document, buffers, _) = gltf::import(path)?;
+
+    // Node transforms are ignored, each primitive is used as authored
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for mesh in document.meshes() {
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                continue;
+            }
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let Some(positions) = reader.read_positions() else {
+                continue;
+            };
+
+            let base = vertices.len() as u32;
+            vertices.extend(positions.map(|position| Vertex {
+                position: Vector3::from(position),
+                color: Vector3::new(1.0, 1.0, 1.0),
+                tex_coords: Vector2::zeros(),
+                normal: Vector3::zeros(),
+            }));
+            let primitive_vertices = &mut vertices[base as usize..];
+            if let Some(tex_coords) = reader.read_tex_coords(0) {
+                // glTF puts the texture origin at the top left
+                for (vertex, uv) in primitive_vertices.iter_mut().zip(tex_coords.into_f32()) {
+                    vertex.tex_coords = Vector2::new(uv[0], 1.0 - uv[1]);
+                }
+            }
+            let has_normals = match reader.read_normals() {
+                Some(normals) => {
+                    for (vertex, normal) in primitive_vertices.iter_mut().zip(normals) {
+                        vertex.normal = Vector3::from(normal);
+                    }
+                    true
+                }
+                None => false,
+            };
+
+            let start = indices.len();
+            match reader.read_indices() {
+                Some(read) => indices.extend(read.into_u32().map(|index| base + index)),
+                None => indices.extend(base..vertices.len() as u32),
+            }
+            if !has_normals {
+                compute_normals(&mut vertices[base as usize..], &indices[start..], base);
+            }
+        }
+    }
+    Ok((vertices, indices))
+}
+
+// Smooth normals from the faces around each vertex
+fn compute_normals(vertices: &mut [Vertex], indices: &[u32], base: u32) {
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| (triangle[i] - base) as usize);
+        if a >= vertices.len() || b >= vertices.len() || c >= vertices.len() {
+            continue;
+        }
+        let face = (vertices[b].position - vertices[a].position)
+            .cross(&(vertices[c].position - vertices[a].position));
+        for index in [a, b, c] {
+            vertices[index].normal += face;
+        }
+    }
+    for vertex in vertices {
+        vertex.normal = vertex
+            .normal
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(Vector3::y);
+    }
+}
+
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ObstacleModels {
+    pub cube: Option<ModelSource>,
+    pub low_bar: Option<ModelSource>,
+    pub tall_wall: Option<ModelSource>,
+    pub high_bar: Option<ModelSource>,
+}
+
+pub struct ObstacleMeshes {
+    cube: MeshHandle,
+    low_bar: MeshHandle,
+    tall_wall: MeshHandle,
+    high_bar: MeshHandle,
+}
+
+impl ObstacleMeshes {
+    pub fn load(registry: &mut MeshRegistry, models: &ObstacleModels) -> Self {
+        Self {
+            cube: registry.insert(
+                "cube",
+                load_or_else(models.cube.as_ref(), || Mesh::cube(Mesh::OBSTACLE_COLOR)),
+            ),
+            low_bar: registry.insert(
+                "lowBar",
+                load_or_else(models.low_bar.as_ref(), Mesh::wide_rectangle),
+            ),
+            tall_wall: registry.insert(
+                "tallWall",
+                load_or_else(models.tall_wall.as_ref(), Mesh::tall_pillar),
+            ),
+            high_bar: registry.insert(
+                "highBar",
+                load_or_else(models.high_bar.as_ref(), Mesh::low_bar),
+            ),
+        }
+    }
+
+    pub fn get(&self, obstacle_type: &ObstacleType) -> MeshHandle {
+        match obstacle_type {
+            ObstacleType::Cube => self.cube,
+            ObstacleType::LowBar => self.low_bar,
+            ObstacleType::TallWall => self.tall_wall,
+            ObstacleType::HighBar => self.high_bar,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SkinConfig {
+    pub model: Option<ModelSource>,
+}
+
+pub fn load_skin_config(name: &str) -> SkinConfig {
+    let path = "assets/skins.json";
+    let data = match resources::read_to_string(path) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Failed to load skin config {}: {}", path, e);
+            return SkinConfig::default();
+        }
+    };
+    match serde_json::from_str::<HashMap<String, SkinConfig>>(&data) {
+        Ok(mut skins) => skins.remove(name).unwrap_or_default(),
+        Err(e) => {
+            eprintln!("Invalid skin config {}: {}", path, e);
+            SkinConfig::default()
+        }
+    }
+}
@@ -0,0 +1,352 @@
+use crate::resources;
+use crate::settings::Quality;
+use crate::shader::{Shader, ShaderError};
+use crate::texture::Texture;
+use gl::types::*;
+use nalgebra::{Matrix4, Vector3, Vector4};
+use rand::Rng;
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct EmitterConfig {
+    // Particles per second for continuous emitters
+    pub rate: f32,
+    // Particles per one-shot burst
+    pub burst: usize,
+    pub offset: [f32; 3],
+    // Half extents of the box particles spawn in
+    pub area: [f32; 3],
+    pub direction: [f32; 3],
+    // 0 keeps the exact direction, 1 scatters in every direction
+    pub spread: f32,
+    pub speed: [f32; 2],
+    pub lifetime: [f32; 2],
+    pub gravity: f32,
+    pub size: [f32; 2],
+    pub color_start: [f32; 4],
+    pub color_end: [f32; 4],
+    // Sample a random patch of the bound texture instead of a round dot
+    pub textured: bool,
+}
+
+impl Default for EmitterConfig {
+    fn default() -> Self {
+        Self {
+            rate: 0.0,
+            burst: 0,
+            offset: [0.0; 3],
+            area: [0.0; 3],
+            direction: [0.0, 1.0, 0.0],
+            spread: 0.5,
+            speed: [1.0, 2.0],
+            lifetime: [0.5, 1.0],
+            gravity: 0.0,
+            size: [0.1, 0.1],
+            color_start: [1.0; 4],
+            color_end: [1.0, 1.0, 1.0, 0.0],
+            textured: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Effects {
+    pub landing_dust: EmitterConfig,
+    pub slide_sparks: EmitterConfig,
+    pub death_burst: EmitterConfig,
+}
+
+impl Effects {
+    pub fn load() -> Self {
+        let path = "assets/particles.json";
+        let data = match resources::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) => {
+                eprintln!("Failed to load particle effects {}: {}", path, e);
+                return Self::default();
+            }
+        };
+        serde_json::from_str(&data).unwrap_or_else(|e| {
+            eprintln!("Invalid particle effects {}: {}", path, e);
+            Self::default()
+        })
+    }
+}
+
+pub struct Emitter {
+    pub config: EmitterConfig,
+    accumulator: f32,
+}
+
+impl Emitter {
+    pub fn new(config: EmitterConfig) -> Self {
+        Self {
+            config,
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, system: &mut ParticleSystem, origin: Vector3<f32>, delta_time: f32) {
+        self.accumulator += self.config.rate * delta_time;
+        let count = self.accumulator as usize;
+        self.accumulator -= count as f32;
+        system.emit(&self.config, origin, count);
+    }
+}
+
+struct Particle {
+    position: Vector3<f32>,
+    velocity: Vector3<f32>,
+    gravity: f32,
+    age: f32,
+    lifetime: f32,
+    size: [f32; 2],
+    color_start: Vector4<f32>,
+    color_end: Vector4<f32>,
+    uv: [f32; 4],
+}
+
+// A particle as drawn, relative to the player like the rest of the scene
+#[repr(C)]
+pub struct ParticleInstance {
+    // Size in w
+    pub position_size: [f32; 4],
+    pub color: [f32; 4],
+    // Patch of the texture, all zero for a round dot
+    pub uv: [f32; 4],
+}
+
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    max_particles: usize,
+}
+
+fn random_range(rng: &mut impl Rng, range: [f32; 2]) -> f32 {
+    if range[1] > range[0] {
+        rng.gen_range(range[0]..range[1])
+    } else {
+        range[0]
+    }
+}
+
+impl ParticleSystem {
+    pub fn new(quality: Quality) -> Self {
+        Self {
+            particles: Vec::new(),
+            max_particles: Self::limit(quality),
+        }
+    }
+
+    fn limit(quality: Quality) -> usize {
+        match quality {
+            Quality::Off => 0,
+            Quality::Low => 500,
+            Quality::Medium => 2000,
+            Quality::High => 5000,
+        }
+    }
+
+    pub fn set_quality(&mut self, quality: Quality) {
+        self.max_particles = Self::limit(quality);
+        self.particles.truncate(self.max_particles);
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    pub fn burst(&mut self, config: &EmitterConfig, origin: Vector3<f32>) {
+        self.emit(config, origin, config.burst);
+    }
+
+    pub fn emit(&mut self, config: &EmitterConfig, origin: Vector3<f32>, count: usize) {
+        let count = count.min(self.max_particles.saturating_sub(self.particles.len()));
+        let mut rng = rand::thread_rng();
+        let direction = Vector3::from(config.direction)
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(Vector3::y);
+
+        for _ in 0..count {
+            let jitter = Vector3::from_fn(|i, _| {
+                let extent = config.area[i];
+                config.offset[i]
+                    + if extent > 0.0 {
+                        rng.gen_range(-extent..extent)
+                    } else {
+                        0.0
+                    }
+            });
+            let scatter = Vector3::from_fn(|_, _| rng.gen_range(-1.0f32..1.0));
+            let heading = (direction + scatter * config.spread)
+                .try_normalize(f32::EPSILON)
+                .unwrap_or(direction);
+            let uv = if config.textured {
+                let size = rng.gen_range(0.1f32..0.25);
+                [
+                    rng.gen_range(0.0..1.0 - size),
+                    rng.gen_range(0.0..1.0 - size),
+                    size,
+                    size,
+                ]
+            } else {
+                [0.0; 4]
+            };
+
+            self.particles.push(Particle {
+                position: origin + jitter,
+                velocity: heading * random_range(&mut rng, config.speed),
+                gravity: config.gravity,
+                age: 0.0,
+                lifetime: random_range(&mut rng, config.lifetime).max(0.01),
+                size: config.size,
+                color_start: Vector4::from(config.color_start),
+                color_end: Vector4::from(config.color_end),
+                uv,
+            });
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        for particle in &mut self.particles {
+            particle.age += delta_time;
+            particle.velocity.y += particle.gravity * delta_time;
+            particle.position += particle.velocity * delta_time;
+        }
+        self.particles.retain(|p| p.age < p.lifetime);
+    }
+
+    pub fn instances(&self, world_z: f32) -> Vec<ParticleInstance> {
+        self.particles
+            .iter()
+            .map(|p| {
+                let t = p.age / p.lifetime;
+                let color = p.color_start.lerp(&p.color_end, t);
+                ParticleInstance {
+                    position_size: [
+                        p.position.x,
+                        p.position.y,
+                        p.position.z - world_z,
+                        p.size[0] + (p.size[1] - p.size[0]) * t,
+                    ],
+                    color: color.into(),
+                    uv: p.uv,
+                }
+            })
+            .collect()
+    }
+}
+
+// Draws particles as camera-facing quads, one instance each
+pub struct ParticleRenderer {
+    shader: Shader,
+    vao: GLuint,
+    quad_vbo: GLuint,
+    instance_vbo: GLuint,
+}
+
+impl Drop for ParticleRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteBuffers(1, &self.quad_vbo);
+            gl::DeleteBuffers(1, &self.instance_vbo);
+        }
+    }
+}
+
+impl ParticleRenderer {
+    pub fn new() -> Result<Self, ShaderError> {
+        let shader = Shader::new(
+            "shaders/vertex/particle.glsl",
+            "shaders/fragment/particle.glsl",
+        )?;
+
+        let corners: [f32; 8] = [-0.5, -0.5, 0.5, -0.5, -0.5, 0.5, 0.5, 0.5];
+        let mut vao = 0;
+        let mut quad_vbo = 0;
+        let mut instance_vbo = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut quad_vbo);
+            gl::GenBuffers(1, &mut instance_vbo);
+            gl::BindVertexArray(vao);
+
+            // Corner (location = 0)
+            gl::BindBuffer(gl::ARRAY_BUFFER, quad_vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(&corners) as isize,
+                corners.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 0, std::ptr::null());
+
+            // Position and size, color, texture rect (locations 1 to 3, one per instance)
+            gl::BindBuffer(gl::ARRAY_BUFFER, instance_vbo);
+            let stride = std::mem::size_of::<ParticleInstance>() as GLsizei;
+            for location in 1..=3 {
+                gl::EnableVertexAttribArray(location);
+                gl::VertexAttribPointer(
+                    location,
+                    4,
+                    gl::FLOAT,
+                    gl::FALSE,
+                    stride,
+                    ((location - 1) as usize * 4 * std::mem::size_of::<f32>()) as *const _,
+                );
+                gl::VertexAttribDivisor(location, 1);
+            }
+
+            gl::BindVertexArray(0);
+        }
+
+        Ok(Self {
+            shader,
+            vao,
+            quad_vbo,
+            instance_vbo,
+        })
+    }
+
+    pub fn shaders_mut(&mut self) -> Vec<&mut Shader> {
+        vec![&mut self.shader]
+    }
+
+    pub unsafe fn render(
+        &self,
+        view: &Matrix4<f32>,
+        projection: &Matrix4<f32>,
+        instances: &[ParticleInstance],
+        texture: &Texture,
+    ) {
+        if instances.is_empty() {
+            return;
+        }
+
+        gl::BindBuffer(gl::ARRAY_BUFFER, self.instance_vbo);
+        gl::BufferData(
+            gl::ARRAY_BUFFER,
+            std::mem::size_of_val(instances) as isize,
+            instances.as_ptr() as *const _,
+            gl::STREAM_DRAW,
+        );
+
+        self.shader.use_program();
+        self.shader.set("view", view);
+        self.shader.set("projection", projection);
+        self.shader.set("particleTexture", &0);
+        texture.bind(0);
+
+        gl::Enable(gl::BLEND);
+        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        gl::DepthMask(gl::FALSE);
+        gl::BindVertexArray(self.vao);
+        gl::DrawArraysInstanced(gl::TRIANGLE_STRIP, 0, 4, instances.len() as GLsizei);
+        gl::BindVertexArray(0);
+        gl::DepthMask(gl::TRUE);
+        gl::Disable(gl::BLEND);
+    }
+}
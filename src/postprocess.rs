@@ -0,0 +1,282 @@
+use crate::framebuffer::{Framebuffer, FramebufferError, RenderTarget};
+use crate::mesh::Mesh;
+use crate::settings::Settings;
+use crate::shader::{Shader, ShaderError};
+use crate::texture::Texture;
+use nalgebra::Vector3;
+use serde::Deserialize;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum PostProcessError {
+    #[error(transparent)]
+    Framebuffer(#[from] FramebufferError),
+    #[error(transparent)]
+    Shader(#[from] ShaderError),
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct ColorGrading {
+    pub tint: [f32; 3],
+    pub exposure: f32,
+    pub contrast: f32,
+    pub saturation: f32,
+}
+
+impl Default for ColorGrading {
+    fn default() -> Self {
+        Self {
+            tint: [1.0, 1.0, 1.0],
+            exposure: 1.0,
+            contrast: 1.0,
+            saturation: 1.0,
+        }
+    }
+}
+
+pub struct PostParams<'a> {
+    // 0 at the starting speed, 1 at top speed
+    pub speed: f32,
+    pub grading: &'a ColorGrading,
+    // 0 for full colour, 1 for greyscale
+    pub desaturation: f32,
+}
+
+#[derive(Clone, Copy)]
+enum Pass {
+    Bloom,
+    RadialBlur,
+    ColorGrading,
+    Vignette,
+    Desaturate,
+}
+
+pub struct PostProcessor {
+    scene: Framebuffer,
+    targets: [Framebuffer; 2],
+    bloom_targets: [Framebuffer; 2],
+    quad: Mesh,
+    bright_shader: Shader,
+    blur_shader: Shader,
+    bloom_shader: Shader,
+    radial_blur_shader: Shader,
+    grading_shader: Shader,
+    vignette_shader: Shader,
+    desaturate_shader: Shader,
+    screen_width: f32,
+    screen_height: f32,
+}
+
+fn post_shader(fragment: &str) -> Result<Shader, ShaderError> {
+    Shader::new(
+        "shaders/vertex/post.glsl",
+        &format!("shaders/fragment/{}.glsl", fragment),
+    )
+}
+
+impl PostProcessor {
+    const BLOOM_THRESHOLD: f32 = 0.85;
+    const BLOOM_INTENSITY: f32 = 0.6;
+    const BLUR_ITERATIONS: usize = 3;
+
+    pub fn new(screen_width: f32, screen_height: f32) -> Option<Self> {
+        Self::create(screen_width, screen_height)
+            .map_err(|e| eprintln!("Post-processing disabled: {}", e))
+            .ok()
+    }
+
+    fn create(screen_width: f32, screen_height: f32) -> Result<Self, PostProcessError> {
+        let width = (screen_width as i32).max(1);
+        let height = (screen_height as i32).max(1);
+        Ok(Self {
+            scene: Framebuffer::color(width, height, true)?,
+            targets: [
+                Framebuffer::color(width, height, false)?,
+                Framebuffer::color(width, height, false)?,
+            ],
+            bloom_targets: [
+                Framebuffer::color((width / 2).max(1), (height / 2).max(1), false)?,
+                Framebuffer::color((width / 2).max(1), (height / 2).max(1), false)?,
+            ],
+            quad: Mesh::quad_2d(),
+            bright_shader: post_shader("bloom_extract")?,
+            blur_shader: post_shader("blur")?,
+            bloom_shader: post_shader("bloom")?,
+            radial_blur_shader: post_shader("radial_blur")?,
+            grading_shader: post_shader("color_grading")?,
+            vignette_shader: post_shader("vignette")?,
+            desaturate_shader: post_shader("desaturate")?,
+            screen_width,
+            screen_height,
+        })
+    }
+
+    pub fn shaders_mut(&mut self) -> Vec<&mut Shader> {
+        vec![
+            &mut self.bright_shader,
+            &mut self.blur_shader,
+            &mut self.bloom_shader,
+            &mut self.radial_blur_shader,
+            &mut self.grading_shader,
+            &mut self.vignette_shader,
+            &mut self.desaturate_shader,
+        ]
+    }
+
+    fn passes(settings: &Settings, params: &PostParams) -> Vec<Pass> {
+        let mut passes = Vec::new();
+        if settings.bloom {
+            passes.push(Pass::Bloom);
+        }
+        if settings.motion_blur && params.speed > 0.0 {
+            passes.push(Pass::RadialBlur);
+        }
+        if settings.color_grading {
+            passes.push(Pass::ColorGrading);
+        }
+        if settings.vignette {
+            passes.push(Pass::Vignette);
+        }
+        if settings.desaturate && params.desaturation > 0.0 {
+            passes.push(Pass::Desaturate);
+        }
+        passes
+    }
+
+    // Redirects the 3D scene into the offscreen target
+    pub fn begin(&self) {
+        self.scene.bind();
+    }
+
+    pub unsafe fn finish(&self, settings: &Settings, params: &PostParams) {
+        gl::Disable(gl::DEPTH_TEST);
+
+        let passes = Self::passes(settings, params);
+        let screen = RenderTarget::Screen {
+            width: self.screen_width,
+            height: self.screen_height,
+        };
+        let mut source = &self.scene;
+        if passes.is_empty() {
+            screen.bind();
+            self.blit(source);
+        }
+
+        for (i, pass) in passes.iter().enumerate() {
+            let next = &self.targets[i % 2];
+            let target = if i + 1 == passes.len() {
+                screen
+            } else {
+                RenderTarget::Framebuffer(next)
+            };
+            self.apply(*pass, color(source), target, params);
+            if i + 1 == passes.len() {
+                break;
+            }
+            source = next;
+        }
+
+        gl::Enable(gl::DEPTH_TEST);
+    }
+
+    unsafe fn blit(&self, source: &Framebuffer) {
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, source.id);
+        gl::BlitFramebuffer(
+            0,
+            0,
+            source.width,
+            source.height,
+            0,
+            0,
+            self.screen_width as i32,
+            self.screen_height as i32,
+            gl::COLOR_BUFFER_BIT,
+            gl::NEAREST,
+        );
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+    }
+
+    unsafe fn apply(
+        &self,
+        pass: Pass,
+        source: &Texture,
+        target: RenderTarget,
+        params: &PostParams,
+    ) {
+        let shader = match pass {
+            Pass::Bloom => {
+                let bloom = self.blur_bright_areas(source);
+                target.bind();
+                self.bloom_shader.use_program();
+                self.bloom_shader.set("bloomTexture", &1);
+                self.bloom_shader.set("intensity", &(Self::BLOOM_INTENSITY));
+                bloom.bind(1);
+                &self.bloom_shader
+            }
+            Pass::RadialBlur => {
+                target.bind();
+                self.radial_blur_shader.use_program();
+                self.radial_blur_shader
+                    .set("strength", &(params.speed * (1.0 - params.desaturation)));
+                &self.radial_blur_shader
+            }
+            Pass::ColorGrading => {
+                target.bind();
+                let grading = params.grading;
+                self.grading_shader.use_program();
+                self.grading_shader
+                    .set("tint", &Vector3::from(grading.tint));
+                self.grading_shader.set("exposure", &grading.exposure);
+                self.grading_shader.set("contrast", &grading.contrast);
+                self.grading_shader.set("saturation", &grading.saturation);
+                &self.grading_shader
+            }
+            Pass::Vignette => {
+                target.bind();
+                self.vignette_shader.use_program();
+                &self.vignette_shader
+            }
+            Pass::Desaturate => {
+                target.bind();
+                self.desaturate_shader.use_program();
+                self.desaturate_shader.set("amount", &params.desaturation);
+                &self.desaturate_shader
+            }
+        };
+
+        shader.set("screenTexture", &0);
+        source.bind(0);
+        self.quad.draw();
+    }
+
+    // Extracts the bright parts of the image at half resolution and blurs them
+    unsafe fn blur_bright_areas(&self, source: &Texture) -> &Texture {
+        self.bloom_targets[0].bind();
+        self.bright_shader.use_program();
+        self.bright_shader.set("screenTexture", &0);
+        self.bright_shader
+            .set("threshold", &(Self::BLOOM_THRESHOLD));
+        source.bind(0);
+        self.quad.draw();
+
+        self.blur_shader.use_program();
+        self.blur_shader.set("screenTexture", &0);
+        for _ in 0..Self::BLUR_ITERATIONS {
+            for (horizontal, from, to) in [(true, 0, 1), (false, 1, 0)] {
+                self.bloom_targets[to].bind();
+                self.blur_shader.set("horizontal", &horizontal);
+                color(&self.bloom_targets[from]).bind(0);
+                self.quad.draw();
+            }
+        }
+        color(&self.bloom_targets[0])
+    }
+}
+
+fn color(framebuffer: &Framebuffer) -> &Texture {
+    framebuffer
+        .color
+        .as_ref()
+        .expect("post-processing targets always have a colour attachment")
+}
@@ -0,0 +1,258 @@
+use crate::resources;
+use crate::shader::ShaderError;
+use std::io;
+
+// Shared snippets named by `#include "file.glsl"`
+const INCLUDE_DIR: &str = "shaders/include";
+
+// GLSL with its includes expanded. `#line` directives number every file by
+// its index in `files`, the shader itself being 0
+pub struct Source {
+    pub code: String,
+    pub files: Vec<String>,
+}
+
+// Expands `#include`s and adds `defines` right after `#version`, which the shader
+// must then have. Each file is only included once
+pub fn preprocess(path: &str, defines: &[(String, String)]) -> Result<Source, ShaderError> {
+    preprocess_with(path, defines, &resources::read_to_string)
+}
+
+fn preprocess_with(
+    path: &str,
+    defines: &[(String, String)],
+    read: &dyn Fn(&str) -> io::Result<String>,
+) -> Result<Source, ShaderError> {
+    let mut source = Source {
+        code: String::new(),
+        files: Vec::new(),
+    };
+    let mut prelude = String::new();
+    for (name, value) in defines {
+        prelude.push_str(&format!("#define {} {}\n", name, value));
+    }
+    let mut prelude = Some(prelude.as_str());
+    expand(path, &mut prelude, read, &mut source, &mut Vec::new())?;
+    if prelude.is_some_and(|prelude| !prelude.is_empty()) {
+        return Err(ShaderError::Include(format!(
+            "{} has no #version line to put its defines after",
+            path
+        )));
+    }
+    Ok(source)
+}
+
+// `prelude` is taken by the first `#version` line
+fn expand(
+    path: &str,
+    prelude: &mut Option<&str>,
+    read: &dyn Fn(&str) -> io::Result<String>,
+    source: &mut Source,
+    stack: &mut Vec<String>,
+) -> Result<(), ShaderError> {
+    let code = read(path).map_err(|e| ShaderError::Io(path.into(), e))?;
+    let index = source.files.len();
+    source.files.push(path.into());
+    stack.push(path.into());
+    if index > 0 {
+        source.code.push_str(&format!("#line 1 {}\n", index));
+    }
+
+    for (number, line) in code.lines().enumerate() {
+        let number = number + 1;
+        let directive = line.trim_start();
+        if directive.starts_with("#version") {
+            // Must stay the first line, defines go after it
+            source.code.push_str(line);
+            source.code.push('\n');
+            if let Some(prelude) = prelude.take() {
+                source.code.push_str(prelude);
+                source
+                    .code
+                    .push_str(&format!("#line {} {}\n", number + 1, index));
+            }
+        } else if let Some(rest) = directive.strip_prefix("#include") {
+            let name = rest
+                .trim()
+                .strip_prefix('"')
+                .and_then(|rest| rest.strip_suffix('"'))
+                .ok_or_else(|| {
+                    ShaderError::Include(format!("{}:{}: malformed #include", path, number))
+                })?;
+            let include = format!("{}/{}", INCLUDE_DIR, name);
+            if stack.contains(&include) {
+                return Err(ShaderError::Include(format!(
+                    "{}:{}: {} includes itself",
+                    path, number, include
+                )));
+            }
+            if !source.files.contains(&include) {
+                expand(&include, &mut None, read, source, stack).map_err(|e| match e {
+                    ShaderError::Io(file, e) => ShaderError::Include(format!(
+                        "{}:{}: cannot include {}: {}",
+                        path, number, file, e
+                    )),
+                    e => e,
+                })?;
+            }
+            source
+                .code
+                .push_str(&format!("#line {} {}\n", number + 1, index));
+        } else {
+            source.code.push_str(line);
+            source.code.push('\n');
+        }
+    }
+
+    stack.pop();
+    Ok(())
+}
+
+impl Source {
+    // Rewrites the `source:line` references of a driver log to file names.
+    // Drivers print them as `0:12`, `0:12(5)` or `0(12)`
+    pub fn map_log(&self, log: &str) -> String {
+        log.trim_end_matches('\0')
+            .lines()
+            .map(|line| self.map_line(line))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn map_line(&self, line: &str) -> String {
+        let bytes = line.as_bytes();
+        let mut start = 0;
+        while start < bytes.len() {
+            let boundary = start == 0 || !bytes[start - 1].is_ascii_alphanumeric();
+            if !bytes[start].is_ascii_digit() || !boundary {
+                start += 1;
+                continue;
+            }
+            let mut end = start;
+            while end < bytes.len() && bytes[end].is_ascii_digit() {
+                end += 1;
+            }
+            let reference = end + 1 < bytes.len()
+                && (bytes[end] == b':' || bytes[end] == b'(')
+                && bytes[end + 1].is_ascii_digit();
+            if reference {
+                if let Some(file) = line[start..end]
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| self.files.get(index))
+                {
+                    return format!("{}{}{}", &line[..start], file, &line[end..]);
+                }
+            }
+            start = end;
+        }
+        line.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn run(files: &[(&str, &str)], defines: &[(&str, &str)]) -> Result<Source, ShaderError> {
+        let files: HashMap<String, String> = files
+            .iter()
+            .map(|&(path, code)| (path.to_string(), code.to_string()))
+            .collect();
+        let defines: Vec<(String, String)> = defines
+            .iter()
+            .map(|&(name, value)| (name.into(), value.into()))
+            .collect();
+        preprocess_with("main.glsl", &defines, &|path| {
+            files
+                .get(path)
+                .cloned()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "missing"))
+        })
+    }
+
+    #[test]
+    fn defines_follow_version() {
+        let source = run(
+            &[("main.glsl", "#version 330 core\nvoid main() {}\n")],
+            &[("SHADOWS", "1")],
+        )
+        .unwrap();
+        assert_eq!(
+            source.code,
+            "#version 330 core\n#define SHADOWS 1\n#line 2 0\nvoid main() {}\n"
+        );
+    }
+
+    #[test]
+    fn defines_without_version() {
+        let result = run(&[("main.glsl", "void main() {}\n")], &[("FOG", "1")]);
+        assert!(matches!(result, Err(ShaderError::Include(_))));
+        assert!(run(&[("main.glsl", "void main() {}\n")], &[]).is_ok());
+    }
+
+    #[test]
+    fn includes_once() {
+        let source = run(
+            &[
+                (
+                    "main.glsl",
+                    "#version 330 core\n#include \"a.glsl\"\n#include \"a.glsl\"\nvoid main() {}\n",
+                ),
+                ("shaders/include/a.glsl", "float a;\n"),
+            ],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(source.files, ["main.glsl", "shaders/include/a.glsl"]);
+        assert_eq!(
+            source.code,
+            "#version 330 core\n#line 2 0\n#line 1 1\nfloat a;\n#line 3 0\n#line 4 0\nvoid main() {}\n"
+        );
+    }
+
+    #[test]
+    fn include_cycle() {
+        let result = run(
+            &[
+                ("main.glsl", "#include \"a.glsl\"\n"),
+                ("shaders/include/a.glsl", "#include \"b.glsl\"\n"),
+                ("shaders/include/b.glsl", "#include \"a.glsl\"\n"),
+            ],
+            &[],
+        );
+        match result {
+            Err(ShaderError::Include(message)) => assert_eq!(
+                message,
+                "shaders/include/b.glsl:1: shaders/include/a.glsl includes itself"
+            ),
+            _ => panic!("the cycle wasn't reported"),
+        }
+    }
+
+    #[test]
+    fn missing_include() {
+        let result = run(&[("main.glsl", "\n#include \"none.glsl\"\n")], &[]);
+        assert!(matches!(
+            result,
+            Err(ShaderError::Include(message)) if message.starts_with("main.glsl:2: cannot include")
+        ));
+    }
+
+    #[test]
+    fn log_lines_name_files() {
+        let source = Source {
+            code: String::new(),
+            files: vec!["main.glsl".into(), "shaders/include/fog.glsl".into()],
+        };
+        let log = "0:12(5): error: x undeclared\n1:3: warning\nERROR: 1(7) bad\n10:2 unknown\0";
+        assert_eq!(
+            source.map_log(log),
+            "main.glsl:12(5): error: x undeclared\n\
+             shaders/include/fog.glsl:3: warning\n\
+             ERROR: shaders/include/fog.glsl(7) bad\n\
+             10:2 unknown"
+        );
+    }
+}
@@ -0,0 +1,318 @@
+use crate::assets::Handle;
+use crate::glyph_atlas;
+use crate::lighting::Lighting;
+use crate::math::{orthographic, scaling, translation};
+use crate::mesh::{Instance, Mesh};
+use crate::particles::{ParticleInstance, ParticleRenderer};
+use crate::postprocess::{PostParams, PostProcessor};
+use crate::settings::{Quality, Settings};
+use crate::shader::{Shader, ShaderError};
+use crate::shadow::ShadowMap;
+use crate::sky::{Background, Fog, Sky};
+use crate::texture::{Texture, TextureArray, TextureOptions};
+use nalgebra::{Matrix4, Vector3};
+
+// What the game draws, independent of the backend. 2D calls are in pixels from the
+// bottom-left corner and go over the 3D scene, which is drawn between `begin_scene`
+// and `end_scene` with the camera, lights and fog given to `begin_scene`
+pub trait Renderer {
+    fn size(&self) -> (f32, f32);
+    fn clear(&mut self, color: Vector3<f32>);
+    fn begin_scene(&mut self, setup: &SceneSetup);
+    fn draw_instances(&mut self, mesh: &Mesh, instances: &[Instance], material: Material);
+    fn draw_particles(&mut self, particles: &[ParticleInstance], texture: &Texture);
+    fn end_scene(&mut self, settings: &Settings, params: &PostParams);
+    fn draw_quad(&mut self, position: (f32, f32), size: (f32, f32), color: Vector3<f32>);
+    // `text` is a `Text` mesh, one unit of it is `scale` pixels
+    fn draw_text(&mut self, text: &Mesh, position: (f32, f32), scale: f32, color: Vector3<f32>);
+}
+
+// Camera, lights and background of a frame, in the player-relative space of the scene
+pub struct SceneSetup<'a> {
+    pub view: Matrix4<f32>,
+    pub projection: Matrix4<f32>,
+    pub eye: Vector3<f32>,
+    pub world_z: f32,
+    pub lighting: &'a Lighting,
+    pub fog: &'a Fog,
+    // Cleared to the fog colour when missing
+    pub background: Option<&'a Background>,
+    pub panorama: Option<&'a Texture>,
+    // What the sun sees, for backends with shadows
+    pub light_space: Matrix4<f32>,
+    pub shadow_casters: &'a [(&'a Mesh, Vec<Instance>)],
+}
+
+// The map theme, with the layer taken from each instance, or a single texture
+#[derive(Clone, Copy)]
+pub enum Material<'a> {
+    Theme(&'a TextureArray),
+    Texture(&'a Texture),
+}
+
+// Features compiled into the game shader, which is rebuilt when they change
+#[derive(Clone, Copy, PartialEq)]
+struct GameShaderVariant {
+    shadows: bool,
+    fog: bool,
+}
+
+impl GameShaderVariant {
+    fn load(self) -> Result<Shader, ShaderError> {
+        let mut defines = Vec::new();
+        if self.shadows {
+            defines.push(("SHADOWS", "1"));
+        }
+        if self.fog {
+            defines.push(("FOG", "1"));
+        }
+        Shader::with_defines(
+            "shaders/vertex/game.glsl",
+            "shaders/fragment/game.glsl",
+            &defines,
+        )
+    }
+}
+
+pub struct GlRenderer {
+    ui_shader: Shader,
+    text_shader: Shader,
+    game_shader: Shader,
+    game_variant: GameShaderVariant,
+    sky: Sky,
+    particles: ParticleRenderer,
+    shadow_map: Option<ShadowMap>,
+    post: Option<PostProcessor>,
+    quad: Mesh,
+    font: Handle<Texture>,
+    // The glyph atlas as last uploaded, with its version
+    atlas: Option<(Texture, u64)>,
+    screen_width: f32,
+    screen_height: f32,
+    ui_projection: Matrix4<f32>,
+    // Camera of the scene being drawn, for the particles
+    view: Matrix4<f32>,
+    projection: Matrix4<f32>,
+}
+
+impl GlRenderer {
+    pub fn new(
+        font: Handle<Texture>,
+        screen_width: f32,
+        screen_height: f32,
+    ) -> Result<Self, ShaderError> {
+        let game_variant = GameShaderVariant {
+            shadows: false,
+            fog: true,
+        };
+        Ok(Self {
+            ui_shader: Shader::new("shaders/vertex/ui.glsl", "shaders/fragment/ui.glsl")?,
+            text_shader: Shader::new("shaders/vertex/text.glsl", "shaders/fragment/text.glsl")?,
+            game_shader: game_variant.load()?,
+            game_variant,
+            sky: Sky::new()?,
+            particles: ParticleRenderer::new()?,
+            shadow_map: None,
+            post: PostProcessor::new(screen_width, screen_height),
+            quad: Mesh::quad_2d(),
+            font,
+            atlas: None,
+            screen_width,
+            screen_height,
+            ui_projection: orthographic(0.0, screen_width, 0.0, screen_height, -1.0, 1.0),
+            view: Matrix4::identity(),
+            projection: Matrix4::identity(),
+        })
+    }
+
+    pub fn resize(&mut self, screen_width: f32, screen_height: f32) {
+        self.screen_width = screen_width;
+        self.screen_height = screen_height;
+        self.ui_projection = orthographic(0.0, screen_width, 0.0, screen_height, -1.0, 1.0);
+        self.post = PostProcessor::new(screen_width, screen_height);
+    }
+
+    pub fn set_shadows(&mut self, quality: Quality) {
+        self.shadow_map = ShadowMap::new(quality);
+    }
+
+    pub fn shaders_mut(&mut self) -> Vec<&mut Shader> {
+        let mut shaders = vec![
+            &mut self.ui_shader,
+            &mut self.text_shader,
+            &mut self.game_shader,
+        ];
+        shaders.extend(self.sky.shaders_mut());
+        shaders.extend(self.particles.shaders_mut());
+        if let Some(shadow_map) = &mut self.shadow_map {
+            shaders.extend(shadow_map.shaders_mut());
+        }
+        if let Some(post) = &mut self.post {
+            shaders.extend(post.shaders_mut());
+        }
+        shaders
+    }
+
+    // Shadows and fog are compiled in or out of the game shader
+    fn update_game_shader(&mut self, fog: &Fog) {
+        let variant = GameShaderVariant {
+            shadows: self.shadow_map.is_some(),
+            fog: fog.density > 0.0,
+        };
+        if variant != self.game_variant {
+            self.game_variant = variant;
+            match variant.load() {
+                Ok(shader) => self.game_shader = shader,
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+    }
+
+    // Uploads the glyph atlas again whenever glyphs were added to it
+    fn update_atlas(&mut self) {
+        glyph_atlas::with_image(|image| {
+            let Some((image, version)) = image else {
+                return;
+            };
+            if !matches!(self.atlas, Some((_, uploaded)) if uploaded == version) {
+                let texture = Texture::from_image(image.clone(), &TextureOptions::ui());
+                self.atlas = Some((texture, version));
+            }
+        });
+    }
+
+    // 2D draws skip the depth test and blend, the 3D passes expect the opposite
+    unsafe fn overlay(draw: impl FnOnce()) {
+        gl::Disable(gl::DEPTH_TEST);
+        gl::Enable(gl::BLEND);
+        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        draw();
+        gl::Disable(gl::BLEND);
+        gl::Enable(gl::DEPTH_TEST);
+    }
+}
+
+impl Renderer for GlRenderer {
+    fn size(&self) -> (f32, f32) {
+        (self.screen_width, self.screen_height)
+    }
+
+    fn clear(&mut self, color: Vector3<f32>) {
+        unsafe {
+            gl::ClearColor(color.x, color.y, color.z, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+    }
+
+    fn begin_scene(&mut self, setup: &SceneSetup) {
+        self.update_game_shader(setup.fog);
+        self.view = setup.view;
+        self.projection = setup.projection;
+        unsafe {
+            if let Some(shadow_map) = &self.shadow_map {
+                shadow_map.begin(&setup.light_space);
+                for (mesh, instances) in setup.shadow_casters {
+                    mesh.draw_instanced(instances);
+                }
+                shadow_map.end(self.screen_width, self.screen_height);
+            }
+
+            if let Some(post) = &self.post {
+                post.begin();
+            }
+            self.clear(Vector3::from(setup.fog.color));
+            if let Some(background) = setup.background {
+                self.sky.render(
+                    setup.fog,
+                    background,
+                    setup.panorama,
+                    &setup.view,
+                    &setup.projection,
+                );
+            }
+
+            let shader = &self.game_shader;
+            shader.use_program();
+            shader.set("view", &setup.view);
+            shader.set("projection", &setup.projection);
+            setup.lighting.apply(shader, setup.world_z, &setup.eye);
+            if setup.fog.density > 0.0 {
+                setup.fog.apply(shader);
+            }
+            if let Some(shadow_map) = &self.shadow_map {
+                shader.set("lightSpaceMatrix", &setup.light_space);
+                shader.set("shadowMap", &1);
+                shadow_map.bind_depth(1);
+            }
+            shader.set("texture_diffuse", &0);
+            shader.set("themeTextures", &2);
+        }
+    }
+
+    fn draw_instances(&mut self, mesh: &Mesh, instances: &[Instance], material: Material) {
+        unsafe {
+            self.game_shader.use_program();
+            match material {
+                Material::Theme(textures) => {
+                    self.game_shader.set("useTheme", &true);
+                    textures.bind(2);
+                }
+                Material::Texture(texture) => {
+                    self.game_shader.set("useTheme", &false);
+                    texture.bind(0);
+                }
+            }
+        }
+        mesh.draw_instanced(instances);
+    }
+
+    fn draw_particles(&mut self, particles: &[ParticleInstance], texture: &Texture) {
+        unsafe {
+            self.particles
+                .render(&self.view, &self.projection, particles, texture)
+        };
+    }
+
+    fn end_scene(&mut self, settings: &Settings, params: &PostParams) {
+        if let Some(post) = &self.post {
+            unsafe { post.finish(settings, params) };
+        }
+    }
+
+    fn draw_quad(&mut self, position: (f32, f32), size: (f32, f32), color: Vector3<f32>) {
+        unsafe {
+            Self::overlay(|| {
+                self.ui_shader.use_program();
+                self.ui_shader.set("projection", &self.ui_projection);
+                self.ui_shader.set(
+                    "model",
+                    &(translation(position.0, position.1, 0.0) * scaling(size.0, size.1, 1.0)),
+                );
+                self.ui_shader.set("color", &color);
+                self.quad.draw();
+            });
+        }
+    }
+
+    fn draw_text(&mut self, text: &Mesh, position: (f32, f32), scale: f32, color: Vector3<f32>) {
+        self.update_atlas();
+        unsafe {
+            Self::overlay(|| {
+                self.text_shader.use_program();
+                self.text_shader.set("projection", &self.ui_projection);
+                self.text_shader.set(
+                    "model",
+                    &(translation(position.0, position.1, 0.0) * scaling(scale, scale, 1.0)),
+                );
+                self.text_shader.set("textColor", &color);
+                self.text_shader.set("text", &0);
+                self.text_shader.set("atlas", &1);
+                self.font.get().bind(0);
+                if let Some((atlas, _)) = &self.atlas {
+                    atlas.bind(1);
+                }
+                text.draw();
+            });
+        }
+    }
+}
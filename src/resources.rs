@@ -0,0 +1,71 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+#[cfg(feature = "embed-assets")]
+static EMBEDDED: &[(&str, &[u8])] = include!(concat!(env!("OUT_DIR"), "/embedded.rs"));
+#[cfg(not(feature = "embed-assets"))]
+static EMBEDDED: &[(&str, &[u8])] = &[];
+
+static ROOTS: OnceLock<Vec<PathBuf>> = OnceLock::new();
+
+pub trait Stream: Read + Seek + Send + Sync {}
+
+impl<T: Read + Seek + Send + Sync> Stream for T {}
+
+// Game files are looked up next to the executable, then in the `--assets`
+// directory, then in the working directory, and finally in the binary itself
+pub fn init(assets_dir: Option<PathBuf>) {
+    let mut roots = Vec::new();
+    if let Some(directory) = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        roots.push(directory);
+    }
+    roots.extend(assets_dir);
+    roots.push(PathBuf::from("."));
+    if ROOTS.set(roots).is_err() {
+        eprintln!("Asset directories are already set");
+    }
+}
+
+// Directories that may hold the game files, in lookup order
+pub fn roots() -> &'static [PathBuf] {
+    ROOTS.get_or_init(|| vec![PathBuf::from(".")])
+}
+
+// The file on disk, for loaders that need a real path
+pub fn find(path: &str) -> Option<PathBuf> {
+    roots()
+        .iter()
+        .map(|root| root.join(path))
+        .find(|candidate| candidate.is_file())
+}
+
+pub fn open(path: &str) -> io::Result<Box<dyn Stream>> {
+    if let Some(file) = find(path) {
+        return Ok(Box::new(File::open(file)?));
+    }
+    match EMBEDDED.iter().find(|(name, _)| *name == path) {
+        Some((_, bytes)) => Ok(Box::new(Cursor::new(*bytes))),
+        None => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} not found", path),
+        )),
+    }
+}
+
+pub fn read(path: &str) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    open(path)?.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+pub fn read_to_string(path: &str) -> io::Result<String> {
+    let mut data = String::new();
+    open(path)?.read_to_string(&mut data)?;
+    Ok(data)
+}
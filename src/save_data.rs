@@ -1,4 +1,6 @@
+use crate::camera::CameraMode;
 use crate::map_select::Maps;
+use crate::settings::Settings;
 use crate::skin_select::Skins;
 use crate::WorldState;
 use serde::{Deserialize, Serialize};
@@ -13,6 +15,10 @@ pub struct SaveData {
     pub quest_progress: HashMap<String, i32>,
     pub current_skin: Skins,
     pub current_map: Maps,
+    #[serde(default)]
+    pub camera_mode: CameraMode,
+    #[serde(default)]
+    pub settings: Settings,
 }
 
 pub fn save_progress(save_data: &SaveData) -> io::Result<()> {
@@ -35,5 +41,7 @@ pub fn extract_save_data(world: &WorldState) -> SaveData {
         quest_progress: world.quest_progress.clone(),
         current_skin: world.current_skin.clone(),
         current_map: world.current_map.clone(),
+        camera_mode: world.run.camera.mode(),
+        settings: world.settings.clone(),
     }
 }
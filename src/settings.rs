@@ -0,0 +1,241 @@
+use crate::audio::AudioSystem;
+use crate::menu::{back_button, BACKGROUND};
+use crate::renderer::Renderer;
+use crate::ui::{Anchor, Button, Grid, Label, Pointer, Slider, Toggle};
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum Quality {
+    Off,
+    Low,
+    Medium,
+    High,
+}
+
+impl Quality {
+    pub fn next(self) -> Self {
+        match self {
+            Quality::Off => Quality::Low,
+            Quality::Low => Quality::Medium,
+            Quality::Medium => Quality::High,
+            Quality::High => Quality::Off,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Quality::Off => "OFF",
+            Quality::Low => "LOW",
+            Quality::Medium => "MEDIUM",
+            Quality::High => "HIGH",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum RenderDistance {
+    Short,
+    Medium,
+    Far,
+    Max,
+}
+
+impl RenderDistance {
+    pub fn next(self) -> Self {
+        match self {
+            RenderDistance::Short => RenderDistance::Medium,
+            RenderDistance::Medium => RenderDistance::Far,
+            RenderDistance::Far => RenderDistance::Max,
+            RenderDistance::Max => RenderDistance::Short,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RenderDistance::Short => "SHORT",
+            RenderDistance::Medium => "MEDIUM",
+            RenderDistance::Far => "FAR",
+            RenderDistance::Max => "MAX",
+        }
+    }
+
+    // Draw distance in world units, the level is generated 1000 units ahead
+    pub fn distance(self) -> f32 {
+        match self {
+            RenderDistance::Short => 150.0,
+            RenderDistance::Medium => 300.0,
+            RenderDistance::Far => 600.0,
+            RenderDistance::Max => 1000.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Settings {
+    pub shadows: Quality,
+    pub particles: Quality,
+    pub render_distance: RenderDistance,
+    pub bloom: bool,
+    pub vignette: bool,
+    pub motion_blur: bool,
+    pub color_grading: bool,
+    pub desaturate: bool,
+    pub music_volume: f32,
+    pub sound_volume: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            shadows: Quality::Medium,
+            particles: Quality::Medium,
+            render_distance: RenderDistance::Far,
+            bloom: true,
+            vignette: true,
+            motion_blur: true,
+            color_grading: true,
+            desaturate: true,
+            music_volume: 0.4,
+            sound_volume: 0.4,
+        }
+    }
+}
+
+// What the caller has to apply after a change, the rest is read from `Settings`
+pub enum SettingsAction {
+    Shadows,
+    Particles,
+    Volume,
+    Back,
+    None,
+}
+
+pub struct SettingsMenu {
+    title: Label,
+    shadows: Button,
+    particles: Button,
+    render_distance: Button,
+    bloom: Toggle,
+    vignette: Toggle,
+    motion_blur: Toggle,
+    color_grading: Toggle,
+    desaturate: Toggle,
+    music: Slider,
+    sounds: Slider,
+    back: Button,
+}
+
+impl SettingsMenu {
+    pub fn new(settings: &Settings) -> Self {
+        // Two columns of options, filled row by row
+        let grid = Grid {
+            anchor: Anchor::Center,
+            offset: (0.0, 0.0),
+            columns: 2,
+            cell: (420.0, 70.0),
+            spacing: (20.0, 20.0),
+        };
+        let color = Vector3::new(0.4, 0.6, 1.0);
+        let button = |i| Button::new("", grid.placement(i, 10), color).text_scale(30.0);
+        let toggle = |i, name, value| Toggle::new(name, value, button(i));
+        let slider = |i, name, value| Slider::new(name, value, grid.placement(i, 10), color);
+
+        let mut menu = SettingsMenu {
+            title: Label::title("SETTINGS"),
+            shadows: button(0),
+            particles: button(1),
+            render_distance: button(2),
+            bloom: toggle(3, "BLOOM", settings.bloom),
+            vignette: toggle(4, "VIGNETTE", settings.vignette),
+            motion_blur: toggle(5, "MOTION BLUR", settings.motion_blur),
+            color_grading: toggle(6, "GRADING", settings.color_grading),
+            desaturate: toggle(7, "DESATURATE", settings.desaturate),
+            music: slider(8, "MUSIC", settings.music_volume),
+            sounds: slider(9, "SOUNDS", settings.sound_volume),
+            back: back_button(),
+        };
+        menu.show(settings);
+        menu
+    }
+
+    // Labels of the options that cycle through values
+    fn show(&mut self, settings: &Settings) {
+        self.shadows
+            .set_text(&format!("SHADOWS: {}", settings.shadows.label()));
+        self.particles
+            .set_text(&format!("PARTICLES: {}", settings.particles.label()));
+        self.render_distance
+            .set_text(&format!("VIEW: {}", settings.render_distance.label()));
+    }
+
+    // Applies clicks and drags to `settings`
+    pub fn update(
+        &mut self,
+        pointer: &Pointer,
+        screen: (f32, f32),
+        audio: &AudioSystem,
+        settings: &mut Settings,
+    ) -> SettingsAction {
+        let mut action = SettingsAction::None;
+        if self.shadows.update(pointer, screen, audio) {
+            settings.shadows = settings.shadows.next();
+            action = SettingsAction::Shadows;
+        }
+        if self.particles.update(pointer, screen, audio) {
+            settings.particles = settings.particles.next();
+            action = SettingsAction::Particles;
+        }
+        if self.render_distance.update(pointer, screen, audio) {
+            settings.render_distance = settings.render_distance.next();
+        }
+        let toggles = [
+            (&mut self.bloom, &mut settings.bloom),
+            (&mut self.vignette, &mut settings.vignette),
+            (&mut self.motion_blur, &mut settings.motion_blur),
+            (&mut self.color_grading, &mut settings.color_grading),
+            (&mut self.desaturate, &mut settings.desaturate),
+        ];
+        for (toggle, value) in toggles {
+            if toggle.update(pointer, screen, audio) {
+                *value = toggle.value;
+            }
+        }
+        let sliders = [
+            (&mut self.music, &mut settings.music_volume),
+            (&mut self.sounds, &mut settings.sound_volume),
+        ];
+        for (slider, value) in sliders {
+            if slider.update(pointer, screen, audio) {
+                *value = slider.value;
+                action = SettingsAction::Volume;
+            }
+        }
+        if self.back.update(pointer, screen, audio) {
+            action = SettingsAction::Back;
+        }
+        self.show(settings);
+        action
+    }
+
+    pub fn render(&self, renderer: &mut dyn Renderer) {
+        renderer.clear(BACKGROUND);
+        self.title.render(renderer);
+        for button in [&self.shadows, &self.particles, &self.render_distance] {
+            button.render(renderer);
+        }
+        for toggle in [
+            &self.bloom,
+            &self.vignette,
+            &self.motion_blur,
+            &self.color_grading,
+            &self.desaturate,
+        ] {
+            toggle.render(renderer);
+        }
+        self.music.render(renderer);
+        self.sounds.render(renderer);
+        self.back.render(renderer);
+    }
+}
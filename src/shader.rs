@@ -1,43 +1,114 @@
+use crate::preprocessor::{self, Source};
 use gl::types::*;
+use nalgebra::{Matrix3, Matrix4, Vector2, Vector3, Vector4};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::ffi::CString;
-use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum ShaderError {
-    #[error("Failed to load shader: {0}")]
-    IoError(#[from] std::io::Error),
+    #[error("Failed to load shader {0}: {1}")]
+    Io(String, std::io::Error),
+    #[error("Shader preprocessing failed: {0}")]
+    Include(String),
     #[error("Shader compilation failed: {0}")]
     CompileError(String),
     #[error("Program linking failed: {0}")]
     LinkError(String),
 }
 
+static DEBUG_UNIFORMS: AtomicBool = AtomicBool::new(false);
+
+// Warns once per program about every uniform set that it doesn't have
+pub fn debug_uniforms(enabled: bool) {
+    DEBUG_UNIFORMS.store(enabled, Ordering::Relaxed);
+}
+
 pub struct Shader {
     pub id: GLuint,
+    vertex_path: String,
+    fragment_path: String,
+    defines: Vec<(String, String)>,
+    // Every file the stages were built from, includes too
+    sources: Vec<String>,
+    uniforms: HashMap<String, GLint>,
+    warned: RefCell<HashSet<String>>,
 }
 
 impl Shader {
     pub fn new(vertex_path: &str, fragment_path: &str) -> Result<Self, ShaderError> {
-        let vertex_code = fs::read_to_string(vertex_path)?;
-        let fragment_code = fs::read_to_string(fragment_path)?;
+        Self::with_defines(vertex_path, fragment_path, &[])
+    }
+
+    // Both stages get `#define name value` for each pair, to build variants of one shader
+    pub fn with_defines(
+        vertex_path: &str,
+        fragment_path: &str,
+        defines: &[(&str, &str)],
+    ) -> Result<Self, ShaderError> {
+        let defines: Vec<(String, String)> = defines
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        let vertex_source = preprocessor::preprocess(vertex_path, &defines)?;
+        let fragment_source = preprocessor::preprocess(fragment_path, &defines)?;
 
-        let vertex_shader = Self::compile_shader(&vertex_code, gl::VERTEX_SHADER)?;
-        let fragment_shader = Self::compile_shader(&fragment_code, gl::FRAGMENT_SHADER)?;
+        let vertex_shader = Self::compile_shader(&vertex_source, gl::VERTEX_SHADER)?;
+        let fragment_shader = match Self::compile_shader(&fragment_source, gl::FRAGMENT_SHADER) {
+            Ok(shader) => shader,
+            Err(e) => {
+                unsafe { gl::DeleteShader(vertex_shader) };
+                return Err(e);
+            }
+        };
 
-        let program = Self::link_program(vertex_shader, fragment_shader)?;
+        let program = Self::link_program(vertex_shader, fragment_shader);
 
         unsafe {
             gl::DeleteShader(vertex_shader);
             gl::DeleteShader(fragment_shader);
         }
+        let program = program?;
+
+        let mut sources = vertex_source.files;
+        sources.extend(fragment_source.files);
+        Ok(Shader {
+            id: program,
+            vertex_path: vertex_path.into(),
+            fragment_path: fragment_path.into(),
+            defines,
+            sources,
+            uniforms: unsafe { active_uniforms(program) },
+            warned: RefCell::new(HashSet::new()),
+        })
+    }
+
+    // Recompiles from disk, keeping the current program if that fails
+    pub fn reload(&mut self) -> Result<(), ShaderError> {
+        let defines: Vec<(&str, &str)> = self
+            .defines
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        let shader = Self::with_defines(&self.vertex_path, &self.fragment_path, &defines)?;
+        *self = shader;
+        Ok(())
+    }
 
-        Ok(Shader { id: program })
+    pub fn uses(&self, path: &Path) -> bool {
+        self.sources.iter().any(|source| Path::new(source) == path)
     }
 
-    fn compile_shader(source: &str, shader_type: GLenum) -> Result<GLuint, ShaderError> {
+    pub fn name(&self) -> String {
+        format!("{} + {}", self.vertex_path, self.fragment_path)
+    }
+
+    fn compile_shader(source: &Source, shader_type: GLenum) -> Result<GLuint, ShaderError> {
         let shader = unsafe { gl::CreateShader(shader_type) };
-        let c_source = CString::new(source.as_bytes()).unwrap();
+        let c_source = CString::new(source.code.as_bytes()).unwrap();
 
         unsafe {
             gl::ShaderSource(shader, 1, &c_source.as_ptr(), std::ptr::null());
@@ -51,9 +122,12 @@ impl Shader {
             let mut len = 0;
             unsafe { gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len) };
             let mut buffer = vec![0; len as usize];
-            unsafe { gl::GetShaderInfoLog(shader, len, &mut len, buffer.as_mut_ptr() as *mut i8) };
+            unsafe {
+                gl::GetShaderInfoLog(shader, len, &mut len, buffer.as_mut_ptr() as *mut i8);
+                gl::DeleteShader(shader);
+            }
             Err(ShaderError::CompileError(
-                String::from_utf8_lossy(&buffer).into_owned(),
+                source.map_log(&String::from_utf8_lossy(&buffer)),
             ))
         } else {
             Ok(shader)
@@ -77,8 +151,9 @@ impl Shader {
             unsafe { gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut len) };
             let mut buffer = vec![0; len as usize];
             unsafe {
-                gl::GetProgramInfoLog(program, len, &mut len, buffer.as_mut_ptr() as *mut i8)
-            };
+                gl::GetProgramInfoLog(program, len, &mut len, buffer.as_mut_ptr() as *mut i8);
+                gl::DeleteProgram(program);
+            }
             Err(ShaderError::LinkError(
                 String::from_utf8_lossy(&buffer).into_owned(),
             ))
@@ -91,25 +166,170 @@ impl Shader {
         gl::UseProgram(self.id);
     }
 
-    pub unsafe fn set_mat4(&self, name: &str, mat: &nalgebra::Matrix4<f32>) {
-        let cname = CString::new(name).unwrap();
-        let location = gl::GetUniformLocation(self.id, cname.as_ptr());
-        gl::UniformMatrix4fv(location, 1, gl::FALSE, mat.as_ptr());
+    // Cached at link time, no allocation or GL query per call
+    fn location(&self, name: &str) -> Option<GLint> {
+        let location = self.uniforms.get(name).copied();
+        if location.is_none()
+            && DEBUG_UNIFORMS.load(Ordering::Relaxed)
+            && self.warned.borrow_mut().insert(name.into())
+        {
+            // Also fires for uniforms the compiler optimised away
+            eprintln!("{} has no active uniform '{}'", self.name(), name);
+        }
+        location
     }
 
-    pub unsafe fn set_vec3(&self, name: &str, vec: &nalgebra::Vector3<f32>) {
-        let cname = CString::new(name).unwrap();
-        let location = gl::GetUniformLocation(self.id, cname.as_ptr());
-        gl::Uniform3f(location, vec.x, vec.y, vec.z);
+    // Works with any GLSL type that has a `Uniform` impl, including arrays.
+    // Applies to the program in use
+    pub fn set<T: Uniform + ?Sized>(&self, name: &str, value: &T) {
+        if let Some(location) = self.location(name) {
+            unsafe { value.apply(location) };
+        }
     }
+}
 
-    pub fn set_int(&self, name: &str, value: i32) {
-        unsafe {
-            let c_str = CString::new(name).unwrap();
-            let location = gl::GetUniformLocation(self.id, c_str.as_ptr());
-            gl::Uniform1i(location, value);
+pub trait Uniform {
+    unsafe fn apply(&self, location: GLint);
+}
+
+impl Uniform for bool {
+    unsafe fn apply(&self, location: GLint) {
+        gl::Uniform1i(location, *self as i32);
+    }
+}
+
+impl Uniform for i32 {
+    unsafe fn apply(&self, location: GLint) {
+        gl::Uniform1i(location, *self);
+    }
+}
+
+impl Uniform for u32 {
+    unsafe fn apply(&self, location: GLint) {
+        gl::Uniform1ui(location, *self);
+    }
+}
+
+impl Uniform for f32 {
+    unsafe fn apply(&self, location: GLint) {
+        gl::Uniform1f(location, *self);
+    }
+}
+
+impl Uniform for Vector2<f32> {
+    unsafe fn apply(&self, location: GLint) {
+        gl::Uniform2f(location, self.x, self.y);
+    }
+}
+
+impl Uniform for Vector3<f32> {
+    unsafe fn apply(&self, location: GLint) {
+        gl::Uniform3f(location, self.x, self.y, self.z);
+    }
+}
+
+impl Uniform for Vector4<f32> {
+    unsafe fn apply(&self, location: GLint) {
+        gl::Uniform4f(location, self.x, self.y, self.z, self.w);
+    }
+}
+
+impl Uniform for Matrix3<f32> {
+    unsafe fn apply(&self, location: GLint) {
+        gl::UniformMatrix3fv(location, 1, gl::FALSE, self.as_ptr());
+    }
+}
+
+impl Uniform for Matrix4<f32> {
+    unsafe fn apply(&self, location: GLint) {
+        gl::UniformMatrix4fv(location, 1, gl::FALSE, self.as_ptr());
+    }
+}
+
+// Arrays are set from `name` or `name[0]`, nalgebra types are tightly packed floats
+impl Uniform for [i32] {
+    unsafe fn apply(&self, location: GLint) {
+        gl::Uniform1iv(location, self.len() as GLsizei, self.as_ptr());
+    }
+}
+
+impl Uniform for [f32] {
+    unsafe fn apply(&self, location: GLint) {
+        gl::Uniform1fv(location, self.len() as GLsizei, self.as_ptr());
+    }
+}
+
+impl Uniform for [Vector2<f32>] {
+    unsafe fn apply(&self, location: GLint) {
+        gl::Uniform2fv(location, self.len() as GLsizei, self.as_ptr() as *const f32);
+    }
+}
+
+impl Uniform for [Vector3<f32>] {
+    unsafe fn apply(&self, location: GLint) {
+        gl::Uniform3fv(location, self.len() as GLsizei, self.as_ptr() as *const f32);
+    }
+}
+
+impl Uniform for [Vector4<f32>] {
+    unsafe fn apply(&self, location: GLint) {
+        gl::Uniform4fv(location, self.len() as GLsizei, self.as_ptr() as *const f32);
+    }
+}
+
+impl Uniform for [Matrix4<f32>] {
+    unsafe fn apply(&self, location: GLint) {
+        gl::UniformMatrix4fv(
+            location,
+            self.len() as GLsizei,
+            gl::FALSE,
+            self.as_ptr() as *const f32,
+        );
+    }
+}
+
+// Locations of every active uniform. Array elements are listed one by one,
+// and the array itself under its bare name
+unsafe fn active_uniforms(program: GLuint) -> HashMap<String, GLint> {
+    let mut uniforms = HashMap::new();
+    let mut count = 0;
+    let mut max_length = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_UNIFORMS, &mut count);
+    gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_length);
+
+    let mut buffer = vec![0u8; max_length.max(1) as usize];
+    for index in 0..count as GLuint {
+        let (mut length, mut size, mut kind) = (0, 0, 0);
+        gl::GetActiveUniform(
+            program,
+            index,
+            buffer.len() as GLsizei,
+            &mut length,
+            &mut size,
+            &mut kind,
+            buffer.as_mut_ptr() as *mut GLchar,
+        );
+        let name = String::from_utf8_lossy(&buffer[..length as usize]).into_owned();
+
+        let base = name.strip_suffix("[0]").unwrap_or(&name).to_string();
+        for element in 0..size {
+            let element_name = if size > 1 || name.ends_with("[0]") {
+                format!("{}[{}]", base, element)
+            } else {
+                base.clone()
+            };
+            let c_name = CString::new(element_name.as_str()).unwrap();
+            let location = gl::GetUniformLocation(program, c_name.as_ptr());
+            // Members of uniform blocks have no location
+            if location >= 0 {
+                uniforms.insert(element_name, location);
+            }
+        }
+        if let Some(&location) = uniforms.get(&format!("{}[0]", base)) {
+            uniforms.entry(base).or_insert(location);
         }
     }
+    uniforms
 }
 
 impl Drop for Shader {
@@ -0,0 +1,90 @@
+use crate::framebuffer::Framebuffer;
+use crate::math;
+use crate::settings::Quality;
+use crate::shader::Shader;
+use nalgebra::{Matrix4, Vector3};
+
+pub struct ShadowMap {
+    framebuffer: Framebuffer,
+    shader: Shader,
+}
+
+impl ShadowMap {
+    // Half size of the area around the player covered by the map
+    const EXTENT: f32 = 40.0;
+    const CENTER: Vector3<f32> = Vector3::new(0.0, 0.0, 25.0);
+
+    pub fn new(quality: Quality) -> Option<Self> {
+        let resolution = match quality {
+            Quality::Off => return None,
+            Quality::Low => 1024,
+            Quality::Medium => 2048,
+            Quality::High => 4096,
+        };
+
+        let framebuffer = match Framebuffer::depth_only(resolution, resolution) {
+            Ok(framebuffer) => framebuffer,
+            Err(e) => {
+                eprintln!("Shadow mapping disabled: {}", e);
+                return None;
+            }
+        };
+        let shader = match Shader::new("shaders/vertex/shadow.glsl", "shaders/fragment/shadow.glsl")
+        {
+            Ok(shader) => shader,
+            Err(e) => {
+                eprintln!("Shadow mapping disabled: {}", e);
+                return None;
+            }
+        };
+
+        Some(ShadowMap {
+            framebuffer,
+            shader,
+        })
+    }
+
+    pub fn shaders_mut(&mut self) -> Vec<&mut Shader> {
+        vec![&mut self.shader]
+    }
+
+    pub fn light_space_matrix(sun_direction: &Vector3<f32>) -> Matrix4<f32> {
+        let direction = sun_direction.normalize();
+        let up = if direction.y.abs() > 0.99 {
+            Vector3::z()
+        } else {
+            Vector3::y()
+        };
+        let eye = Self::CENTER - direction * Self::EXTENT * 2.0;
+        let view = math::look_at(eye, Self::CENTER, up);
+        let projection = math::orthographic(
+            -Self::EXTENT,
+            Self::EXTENT,
+            -Self::EXTENT,
+            Self::EXTENT,
+            0.1,
+            Self::EXTENT * 4.0,
+        );
+        projection * view
+    }
+
+    pub unsafe fn begin(&self, light_space: &Matrix4<f32>) {
+        self.framebuffer.bind();
+        gl::Clear(gl::DEPTH_BUFFER_BIT);
+        gl::Enable(gl::POLYGON_OFFSET_FILL);
+        gl::PolygonOffset(2.0, 4.0);
+        self.shader.use_program();
+        self.shader.set("lightSpaceMatrix", light_space);
+    }
+
+    pub unsafe fn end(&self, screen_width: f32, screen_height: f32) {
+        gl::Disable(gl::POLYGON_OFFSET_FILL);
+        Framebuffer::bind_default(screen_width, screen_height);
+    }
+
+    pub fn bind_depth(&self, unit: u32) {
+        if let Some(depth) = &self.framebuffer.depth {
+            depth.bind(unit);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use crate::mesh::Mesh;
+use crate::shader::{Shader, ShaderError};
+use crate::texture::Texture;
+use nalgebra::{Matrix4, Vector3};
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct Fog {
+    pub color: [f32; 3],
+    pub start: f32,
+    pub density: f32,
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Self {
+            color: [0.1, 0.1, 0.1],
+            start: 100.0,
+            density: 0.007,
+        }
+    }
+}
+
+impl Fog {
+    pub unsafe fn apply(&self, shader: &Shader) {
+        shader.set("fogColor", &Vector3::from(self.color));
+        shader.set("fogStart", &self.start);
+        shader.set("fogDensity", &self.density);
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Background {
+    // Vertical gradient that fades into the fog colour at the horizon
+    Gradient { top: [f32; 3], bottom: [f32; 3] },
+    // Equirectangular image wrapped around the camera
+    Panorama { texture: String },
+}
+
+pub struct Sky {
+    shader: Shader,
+    mesh: Mesh,
+}
+
+impl Sky {
+    pub fn new() -> Result<Self, ShaderError> {
+        Ok(Self {
+            shader: Shader::new("shaders/vertex/sky.glsl", "shaders/fragment/sky.glsl")?,
+            mesh: Mesh::quad_2d(),
+        })
+    }
+
+    pub fn shaders_mut(&mut self) -> Vec<&mut Shader> {
+        vec![&mut self.shader]
+    }
+
+    pub unsafe fn render(
+        &self,
+        fog: &Fog,
+        background: &Background,
+        panorama: Option<&Texture>,
+        view: &Matrix4<f32>,
+        projection: &Matrix4<f32>,
+    ) {
+        // Only the rotation matters for a background at infinity
+        let mut rotation = *view;
+        rotation.fixed_view_mut::<3, 1>(0, 3).fill(0.0);
+        let inverse_view_projection = (projection * rotation)
+            .try_inverse()
+            .unwrap_or_else(Matrix4::identity);
+
+        self.shader.use_program();
+        self.shader
+            .set("inverseViewProjection", &inverse_view_projection);
+        self.shader.set("horizonColor", &Vector3::from(fog.color));
+        match (background, panorama) {
+            (Background::Panorama { .. }, Some(texture)) => {
+                self.shader.set("useTexture", &true);
+                self.shader.set("panorama", &0);
+                texture.bind(0);
+            }
+            (Background::Gradient { top, bottom }, _) => {
+                self.shader.set("useTexture", &false);
+                self.shader.set("topColor", &Vector3::from(*top));
+                self.shader.set("bottomColor", &Vector3::from(*bottom));
+            }
+            (Background::Panorama { .. }, None) => return,
+        }
+
+        gl::Disable(gl::DEPTH_TEST);
+        gl::DepthMask(gl::FALSE);
+        self.mesh.draw();
+        gl::DepthMask(gl::TRUE);
+        gl::Enable(gl::DEPTH_TEST);
+    }
+}
@@ -0,0 +1,559 @@
+use crate::glyph_atlas;
+use crate::lighting::{Lighting, PointLight};
+use crate::mesh::{Instance, Mesh};
+use crate::particles::ParticleInstance;
+use crate::postprocess::PostParams;
+use crate::renderer::{Material, Renderer, SceneSetup};
+use crate::settings::Settings;
+use crate::sky::{Background, Fog};
+use crate::texture::{Filter, Image, Texture, TextureOptions, Wrap};
+use nalgebra::{Matrix3, Matrix4, Vector2, Vector3, Vector4};
+use std::f32::consts::PI;
+
+// Rasterizes on the CPU into an image, for screenshots and tests without a GPU.
+// The scene is lit per pixel like the game shader but has no shadows or
+// post-processing, and textures are sampled without mipmaps
+pub struct SoftwareRenderer {
+    canvas: Canvas,
+    font: Image,
+    scene: SceneState,
+}
+
+// What `begin_scene` was given, kept for the draws that follow
+struct SceneState {
+    view: Matrix4<f32>,
+    view_projection: Matrix4<f32>,
+    eye: Vector3<f32>,
+    lighting: Lighting,
+    // Positions relative to the player
+    point_lights: Vec<(Vector3<f32>, PointLight)>,
+    fog: Fog,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Depth {
+    Off,
+    Test,
+    // Tests and keeps the depth of what was drawn
+    Write,
+}
+
+// A corner of a triangle after the vertex stage, or a point interpolated between them
+#[derive(Clone)]
+struct ClipVertex {
+    clip: Vector4<f32>,
+    position: Vector3<f32>,
+    normal: Vector3<f32>,
+    uv: Vector2<f32>,
+}
+
+struct Canvas {
+    width: u32,
+    height: u32,
+    // RGBA8, bottom row first like `Image`
+    pixels: Vec<u8>,
+    // Normalized device depth, -1 at the near plane
+    depth: Vec<f32>,
+}
+
+impl SoftwareRenderer {
+    pub fn new(width: u32, height: u32, font: Image) -> Self {
+        Self {
+            canvas: Canvas::new(width, height),
+            font,
+            scene: SceneState {
+                view: Matrix4::identity(),
+                view_projection: Matrix4::identity(),
+                eye: Vector3::zeros(),
+                lighting: Lighting::default(),
+                point_lights: Vec::new(),
+                fog: Fog::default(),
+            },
+        }
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.canvas = Canvas::new(width, height);
+    }
+
+    pub fn image(&self) -> Image {
+        Image {
+            width: self.canvas.width,
+            height: self.canvas.height,
+            pixels: self.canvas.pixels.clone(),
+        }
+    }
+
+    // Same as the sky shader, a gradient or panorama looked up by view direction
+    fn draw_sky(&mut self, setup: &SceneSetup, background: &Background) {
+        let panorama = match (background, setup.panorama.and_then(Texture::image)) {
+            (Background::Panorama { .. }, None) => return,
+            (_, panorama) => panorama,
+        };
+        // Only the rotation matters for a background at infinity
+        let mut rotation = setup.view;
+        rotation.fixed_view_mut::<3, 1>(0, 3).fill(0.0);
+        let inverse_view_projection = (setup.projection * rotation)
+            .try_inverse()
+            .unwrap_or_else(Matrix4::identity);
+        let horizon = Vector3::from(setup.fog.color);
+
+        let canvas = &mut self.canvas;
+        for y in 0..canvas.height {
+            for x in 0..canvas.width {
+                let ndc = Vector4::new(
+                    (x as f32 + 0.5) / canvas.width as f32 * 2.0 - 1.0,
+                    (y as f32 + 0.5) / canvas.height as f32 * 2.0 - 1.0,
+                    1.0,
+                    1.0,
+                );
+                let far = inverse_view_projection * ndc;
+                let direction = (far.xyz() / far.w).normalize();
+                let color = match (background, panorama) {
+                    (Background::Panorama { .. }, Some((image, options))) => {
+                        let uv = Vector2::new(
+                            direction.x.atan2(direction.z) / (2.0 * PI) + 0.5,
+                            direction.y.clamp(-1.0, 1.0).asin() / PI + 0.5,
+                        );
+                        sample_texture(image, options, uv).xyz()
+                    }
+                    (Background::Gradient { top, .. }, _) if direction.y >= 0.0 => {
+                        let amount = smoothstep(0.0, 0.6, direction.y);
+                        horizon.lerp(&Vector3::from(*top), amount)
+                    }
+                    (Background::Gradient { bottom, .. }, _) => {
+                        let amount = smoothstep(0.0, 0.3, -direction.y);
+                        horizon.lerp(&Vector3::from(*bottom), amount)
+                    }
+                    (Background::Panorama { .. }, None) => horizon,
+                };
+                canvas.blend((y * canvas.width + x) as usize, color, 1.0);
+            }
+        }
+    }
+}
+
+impl ClipVertex {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            clip: self.clip.lerp(&other.clip, t),
+            position: self.position.lerp(&other.position, t),
+            normal: self.normal.lerp(&other.normal, t),
+            uv: self.uv.lerp(&other.uv, t),
+        }
+    }
+
+    fn weighted(corners: [&Self; 3], weights: Vector3<f32>) -> Self {
+        let [a, b, c] = corners;
+        let (x, y, z) = (weights.x, weights.y, weights.z);
+        Self {
+            clip: a.clip * x + b.clip * y + c.clip * z,
+            position: a.position * x + b.position * y + c.position * z,
+            normal: a.normal * x + b.normal * y + c.normal * z,
+            uv: a.uv * x + b.uv * y + c.uv * z,
+        }
+    }
+}
+
+impl SceneState {
+    // The game shader without shadows
+    fn shade(&self, albedo: Vector3<f32>, vertex: &ClipVertex) -> Vector3<f32> {
+        let lighting = &self.lighting;
+        let to_eye = self.eye - vertex.position;
+        let view_direction = to_eye
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(Vector3::z);
+        let mut normal = vertex
+            .normal
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(Vector3::y);
+        // Walls, floor and ceiling are single planes seen from either side
+        if normal.dot(&view_direction) < 0.0 {
+            normal = -normal;
+        }
+
+        let sun = -Vector3::from(lighting.sun_direction).normalize();
+        let mut light = Vector3::from(lighting.ambient)
+            + self.blinn_phong(&normal, &view_direction, &sun, lighting.sun_color);
+        for (position, point_light) in &self.point_lights {
+            let to_light = position - vertex.position;
+            let distance = to_light.norm();
+            let falloff = (1.0 - (distance / point_light.range).powi(2)).clamp(0.0, 1.0);
+            let direction = to_light / distance;
+            light += self.blinn_phong(&normal, &view_direction, &direction, point_light.color)
+                * falloff
+                * falloff;
+        }
+
+        let color = albedo.component_mul(&light);
+        let fog = &self.fog;
+        if fog.density <= 0.0 {
+            return color;
+        }
+        // Exponential squared, starting at `start` from the viewer
+        let fog_distance = (to_eye.norm() - fog.start).max(0.0) * fog.density;
+        let amount = 1.0 - (-fog_distance * fog_distance).exp();
+        color.lerp(&Vector3::from(fog.color), amount)
+    }
+
+    fn blinn_phong(
+        &self,
+        normal: &Vector3<f32>,
+        view_direction: &Vector3<f32>,
+        light_direction: &Vector3<f32>,
+        color: [f32; 3],
+    ) -> Vector3<f32> {
+        let diffuse = normal.dot(light_direction).max(0.0);
+        let halfway = (light_direction + view_direction)
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(Vector3::zeros);
+        let specular = normal.dot(&halfway).max(0.0).powf(self.lighting.shininess)
+            * self.lighting.specular_strength;
+        Vector3::from(color) * (diffuse + specular)
+    }
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Self {
+        let (width, height) = (width.max(1), height.max(1));
+        let size = (width * height) as usize;
+        Self {
+            width,
+            height,
+            pixels: vec![0; size * 4],
+            depth: vec![1.0; size],
+        }
+    }
+
+    fn blend(&mut self, index: usize, color: Vector3<f32>, alpha: f32) {
+        let pixel = &mut self.pixels[index * 4..index * 4 + 4];
+        for (channel, value) in pixel.iter_mut().zip(color.iter()) {
+            let blended = value * alpha + *channel as f32 / 255.0 * (1.0 - alpha);
+            *channel = to_byte(blended);
+        }
+        pixel[3] = 255;
+    }
+
+    fn to_screen(&self, clip: &Vector4<f32>) -> Vector3<f32> {
+        let ndc = clip.xyz() / clip.w;
+        Vector3::new(
+            (ndc.x + 1.0) / 2.0 * self.width as f32,
+            (ndc.y + 1.0) / 2.0 * self.height as f32,
+            ndc.z,
+        )
+    }
+
+    // Fills the pixels whose centre is inside the triangle. Corners are in pixels with
+    // the depth in z, `shade` gets barycentric weights and returns a colour and alpha
+    fn triangle(
+        &mut self,
+        corners: [Vector3<f32>; 3],
+        depth: Depth,
+        shade: impl Fn(Vector3<f32>) -> Option<(Vector3<f32>, f32)>,
+    ) {
+        let [mut a, mut b, c] = corners;
+        let mut area = edge(&a, &b, &c);
+        if area == 0.0 {
+            return;
+        }
+        // Counter-clockwise from here on, so the fill rule has a single case
+        let flipped = area < 0.0;
+        if flipped {
+            std::mem::swap(&mut a, &mut b);
+            area = -area;
+        }
+
+        let min_x = a.x.min(b.x).min(c.x).floor().max(0.0) as u32;
+        let min_y = a.y.min(b.y).min(c.y).floor().max(0.0) as u32;
+        let max_x = (a.x.max(b.x).max(c.x).ceil().max(0.0) as u32).min(self.width);
+        let max_y = (a.y.max(b.y).max(c.y).ceil().max(0.0) as u32).min(self.height);
+        let edges = [(&b, &c), (&c, &a), (&a, &b)];
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let point = Vector3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
+                let weights = edges.map(|(from, to)| edge(from, to, &point));
+                let inside = weights.iter().zip(edges).all(|(&weight, (from, to))| {
+                    weight > 0.0 || (weight == 0.0 && top_left(from, to))
+                });
+                if !inside {
+                    continue;
+                }
+
+                let mut barycentric = Vector3::from(weights) / area;
+                let z = barycentric.x * a.z + barycentric.y * b.z + barycentric.z * c.z;
+                let index = (y * self.width + x) as usize;
+                if depth != Depth::Off && (z >= self.depth[index] || z < -1.0) {
+                    continue;
+                }
+                if flipped {
+                    barycentric.swap_rows(0, 1);
+                }
+                if let Some((color, alpha)) = shade(barycentric) {
+                    if depth == Depth::Write {
+                        self.depth[index] = z;
+                    }
+                    self.blend(index, color, alpha);
+                }
+            }
+        }
+    }
+
+    // Clips a triangle against the near plane and fills it, `shade` gets the
+    // attributes interpolated with perspective correction
+    fn rasterize(
+        &mut self,
+        triangle: &[ClipVertex; 3],
+        depth: Depth,
+        shade: impl Fn(&ClipVertex) -> Option<(Vector3<f32>, f32)>,
+    ) {
+        let polygon = clip_near(triangle);
+        for i in 1..polygon.len().saturating_sub(1) {
+            let corners = [&polygon[0], &polygon[i], &polygon[i + 1]];
+            let screen = corners.map(|vertex| self.to_screen(&vertex.clip));
+            let inverse_w = Vector3::from(corners.map(|vertex| 1.0 / vertex.clip.w));
+            self.triangle(screen, depth, |weights| {
+                let weights = weights.component_mul(&inverse_w);
+                let weights = weights / weights.sum();
+                shade(&ClipVertex::weighted(corners, weights))
+            });
+        }
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    fn size(&self) -> (f32, f32) {
+        (self.canvas.width as f32, self.canvas.height as f32)
+    }
+
+    fn clear(&mut self, color: Vector3<f32>) {
+        let rgba = [color.x, color.y, color.z, 1.0].map(to_byte);
+        for pixel in self.canvas.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&rgba);
+        }
+        self.canvas.depth.fill(1.0);
+    }
+
+    fn begin_scene(&mut self, setup: &SceneSetup) {
+        self.scene = SceneState {
+            view: setup.view,
+            view_projection: setup.projection * setup.view,
+            eye: setup.eye,
+            lighting: setup.lighting.clone(),
+            point_lights: setup
+                .lighting
+                .visible_point_lights(setup.world_z)
+                .into_iter()
+                .map(|(position, light)| (position, light.clone()))
+                .collect(),
+            fog: setup.fog.clone(),
+        };
+        self.clear(Vector3::from(setup.fog.color));
+        if let Some(background) = setup.background {
+            self.draw_sky(setup, background);
+        }
+    }
+
+    fn draw_instances(&mut self, mesh: &Mesh, instances: &[Instance], material: Material) {
+        let vertices = mesh.vertices();
+        for instance in instances {
+            let image = match material {
+                Material::Theme(textures) => textures
+                    .layer(instance.layer.round() as usize)
+                    .map(|image| (image, textures.options())),
+                Material::Texture(texture) => texture.image(),
+            };
+            let transform = self.scene.view_projection * instance.model;
+            let normal_matrix = instance
+                .model
+                .fixed_view::<3, 3>(0, 0)
+                .try_inverse()
+                .unwrap_or_else(Matrix3::identity)
+                .transpose();
+
+            for triangle in mesh.indices().chunks_exact(3) {
+                let corners = [0, 1, 2].map(|i| {
+                    let vertex = &vertices[triangle[i] as usize];
+                    let position = vertex.position.push(1.0);
+                    ClipVertex {
+                        clip: transform * position,
+                        position: (instance.model * position).xyz(),
+                        normal: normal_matrix * vertex.normal,
+                        uv: vertex.tex_coords,
+                    }
+                });
+                let scene = &self.scene;
+                self.canvas.rasterize(&corners, Depth::Write, |vertex| {
+                    let albedo = image.map_or(Vector4::repeat(1.0), |(image, options)| {
+                        sample_texture(image, options, vertex.uv)
+                    });
+                    Some((scene.shade(albedo.xyz(), vertex), 1.0))
+                });
+            }
+        }
+    }
+
+    fn draw_particles(&mut self, particles: &[ParticleInstance], texture: &Texture) {
+        // Billboards like the particle shader, along the camera's right and up axes
+        let right = self.scene.view.fixed_view::<1, 3>(0, 0).transpose();
+        let up = self.scene.view.fixed_view::<1, 3>(1, 0).transpose();
+        let image = texture.image();
+        for particle in particles {
+            let [x, y, z, size] = particle.position_size;
+            let center = Vector3::new(x, y, z);
+            let color = Vector4::from(particle.color);
+            let corners = [(-0.5, -0.5), (0.5, -0.5), (-0.5, 0.5), (0.5, 0.5)].map(|(cx, cy)| {
+                let position = center + (right * cx + up * cy) * size;
+                ClipVertex {
+                    clip: self.scene.view_projection * position.push(1.0),
+                    position,
+                    normal: Vector3::zeros(),
+                    uv: Vector2::new(cx + 0.5, cy + 0.5),
+                }
+            });
+            let [u, v, width, height] = particle.uv;
+            for [a, b, c] in [[0, 1, 2], [2, 1, 3]] {
+                let triangle = [a, b, c].map(|i| corners[i].clone());
+                self.canvas.rasterize(&triangle, Depth::Test, |vertex| {
+                    let (rgb, alpha) = match image {
+                        Some((image, options)) if width > 0.0 => {
+                            let uv = Vector2::new(u, v)
+                                + vertex.uv.component_mul(&Vector2::new(width, height));
+                            let texel = sample_texture(image, options, uv).component_mul(&color);
+                            (texel.xyz(), texel.w)
+                        }
+                        _ => {
+                            let distance = (vertex.uv - Vector2::repeat(0.5)).norm();
+                            let falloff = 1.0 - smoothstep(0.3, 0.5, distance);
+                            (color.xyz(), color.w * falloff)
+                        }
+                    };
+                    (alpha > 0.0).then_some((rgb, alpha))
+                });
+            }
+        }
+    }
+
+    // No post-processing on the CPU
+    fn end_scene(&mut self, _settings: &Settings, _params: &PostParams) {}
+
+    fn draw_quad(&mut self, position: (f32, f32), size: (f32, f32), color: Vector3<f32>) {
+        // Same coverage as two triangles: the pixels whose centre is inside
+        let first = |start: f32| (start - 0.5).ceil().max(0.0) as u32;
+        let x_end = first(position.0 + size.0).min(self.canvas.width);
+        let y_end = first(position.1 + size.1).min(self.canvas.height);
+        for y in first(position.1)..y_end {
+            for x in first(position.0)..x_end {
+                let index = (y * self.canvas.width + x) as usize;
+                self.canvas.blend(index, color, 1.0);
+            }
+        }
+    }
+
+    fn draw_text(&mut self, text: &Mesh, position: (f32, f32), scale: f32, color: Vector3<f32>) {
+        let vertices = text.vertices();
+        glyph_atlas::with_image(|atlas| {
+            for triangle in text.indices().chunks_exact(3) {
+                let corners = [0, 1, 2].map(|i| &vertices[triangle[i] as usize]);
+                let screen = corners.map(|vertex| {
+                    Vector3::new(
+                        position.0 + vertex.position.x * scale,
+                        position.1 + vertex.position.y * scale,
+                        0.0,
+                    )
+                });
+                // Same as the text shader, the colour attribute picks the texture
+                let image = match atlas {
+                    Some((atlas, _)) if corners[0].color.x > 0.5 => atlas,
+                    _ => &self.font,
+                };
+                self.canvas.triangle(screen, Depth::Off, |weights| {
+                    let uv = corners[0].tex_coords * weights.x
+                        + corners[1].tex_coords * weights.y
+                        + corners[2].tex_coords * weights.z;
+                    let texel = sample(image, uv);
+                    (texel.w > 0.0).then(|| (color.component_mul(&texel.xyz()), texel.w))
+                });
+            }
+        });
+    }
+}
+
+fn to_byte(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn sample(image: &Image, uv: Vector2<f32>) -> Vector4<f32> {
+    let x = ((uv.x * image.width as f32).max(0.0) as u32).min(image.width - 1);
+    let y = ((uv.y * image.height as f32).max(0.0) as u32).min(image.height - 1);
+    let index = ((y * image.width + x) * 4) as usize;
+    Vector4::from_fn(|channel, _| image.pixels[index + channel] as f32 / 255.0)
+}
+
+fn edge(from: &Vector3<f32>, to: &Vector3<f32>, point: &Vector3<f32>) -> f32 {
+    (to.x - from.x) * (point.y - from.y) - (to.y - from.y) * (point.x - from.x)
+}
+
+// Pixels exactly on an edge only belong to the triangle on its top or left side,
+// so shared edges aren't blended twice
+fn top_left(from: &Vector3<f32>, to: &Vector3<f32>) -> bool {
+    (from.y == to.y && to.x < from.x) || to.y < from.y
+}
+
+// Cuts away the part of a clip-space triangle behind the near plane
+fn clip_near(triangle: &[ClipVertex; 3]) -> Vec<ClipVertex> {
+    let distance = |vertex: &ClipVertex| vertex.clip.z + vertex.clip.w;
+    let mut polygon = Vec::with_capacity(4);
+    for i in 0..3 {
+        let (current, next) = (&triangle[i], &triangle[(i + 1) % 3]);
+        let (d0, d1) = (distance(current), distance(next));
+        if d0 >= 0.0 {
+            polygon.push(current.clone());
+        }
+        if (d0 >= 0.0) != (d1 >= 0.0) {
+            polygon.push(current.lerp(next, d0 / (d0 - d1)));
+        }
+    }
+    polygon
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// GL sampling without mipmaps: wraps the coordinates and filters as the options ask
+fn sample_texture(image: &Image, options: &TextureOptions, uv: Vector2<f32>) -> Vector4<f32> {
+    let texel = |x: i64, y: i64| {
+        let x = wrap(x, image.width, options.wrap);
+        let y = wrap(y, image.height, options.wrap);
+        let index = ((y * image.width + x) * 4) as usize;
+        Vector4::from_fn(|channel, _| image.pixels[index + channel] as f32 / 255.0)
+    };
+    let x = uv.x * image.width as f32;
+    let y = uv.y * image.height as f32;
+    match options.filter {
+        Filter::Nearest => texel(x.floor() as i64, y.floor() as i64),
+        Filter::Linear => {
+            let (x, y) = (x - 0.5, y - 0.5);
+            let (x0, y0) = (x.floor() as i64, y.floor() as i64);
+            let (fx, fy) = (x - x.floor(), y - y.floor());
+            let bottom = texel(x0, y0).lerp(&texel(x0 + 1, y0), fx);
+            let top = texel(x0, y0 + 1).lerp(&texel(x0 + 1, y0 + 1), fx);
+            bottom.lerp(&top, fy)
+        }
+    }
+}
+
+fn wrap(coordinate: i64, size: u32, wrap: Wrap) -> u32 {
+    let size = size as i64;
+    let wrapped = match wrap {
+        Wrap::Repeat => coordinate.rem_euclid(size),
+        Wrap::MirroredRepeat => {
+            let period = coordinate.rem_euclid(2 * size);
+            period.min(2 * size - 1 - period)
+        }
+        Wrap::ClampToEdge => coordinate.clamp(0, size - 1),
+    };
+    wrapped as u32
+}
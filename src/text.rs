@@ -0,0 +1,392 @@
+use crate::glyph_atlas::{self, GLYPH_PIXELS};
+use crate::mesh::{Mesh, Vertex};
+use crate::resources;
+use nalgebra::{Vector2, Vector3};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use thiserror::Error;
+
+// BMFont text descriptor for the font texture
+const FONT_DESCRIPTOR: &str = "assets/fonts/MinecraftRegular.fnt";
+
+static FONT: OnceLock<Font> = OnceLock::new();
+
+#[derive(Error, Debug)]
+pub enum FontError {
+    #[error("Failed to open font {0}: {1}")]
+    Io(String, std::io::Error),
+    #[error("Invalid font {0} line {1}: {2}")]
+    Parse(String, usize, String),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+}
+
+// Where a character is in its texture and how it sits on the line, in font pixels
+#[derive(Clone, Copy)]
+struct Glyph {
+    // Texture rectangle, bottom-left and top-right
+    uv_min: Vector2<f32>,
+    uv_max: Vector2<f32>,
+    width: f32,
+    height: f32,
+    x_offset: f32,
+    y_offset: f32,
+    x_advance: f32,
+    // Rasterized into the glyph atlas rather than taken from the font texture
+    atlas: bool,
+}
+
+impl Glyph {
+    // The texture is stored bottom row first, BMFont rectangles are from the top
+    fn from_rect(x: f32, y: f32, width: f32, height: f32, texture_size: Vector2<f32>) -> Self {
+        Glyph {
+            uv_min: Vector2::new(x / texture_size.x, 1.0 - (y + height) / texture_size.y),
+            uv_max: Vector2::new((x + width) / texture_size.x, 1.0 - y / texture_size.y),
+            width,
+            height,
+            x_offset: 0.0,
+            y_offset: 0.0,
+            x_advance: width,
+            atlas: false,
+        }
+    }
+}
+
+pub struct Font {
+    // Pixels per text unit, the height of a glyph cell
+    size: f32,
+    line_height: f32,
+    // Baseline from the top of the cell
+    base: f32,
+    texture_width: f32,
+    texture_height: f32,
+    glyphs: HashMap<char, Glyph>,
+    kerning: HashMap<(char, char), f32>,
+}
+
+impl Font {
+    pub fn load(path: &str) -> Result<Self, FontError> {
+        let data = resources::read_to_string(path).map_err(|e| FontError::Io(path.into(), e))?;
+        let mut font = Font {
+            size: 0.0,
+            line_height: 0.0,
+            base: 0.0,
+            texture_width: 0.0,
+            texture_height: 0.0,
+            glyphs: HashMap::new(),
+            kerning: HashMap::new(),
+        };
+
+        for (line_number, line) in data.lines().enumerate() {
+            let error = |message: String| FontError::Parse(path.into(), line_number + 1, message);
+            let mut tokens = line.split_whitespace();
+            let Some(tag) = tokens.next() else {
+                continue;
+            };
+            let values: HashMap<&str, &str> =
+                tokens.filter_map(|token| token.split_once('=')).collect();
+            let number = |key: &str| -> Result<f32, FontError> {
+                values
+                    .get(key)
+                    .and_then(|value| value.parse().ok())
+                    .ok_or_else(|| error(format!("missing or invalid {}", key)))
+            };
+            let character = |key: &str| -> Result<char, FontError> {
+                char::from_u32(number(key)? as u32)
+                    .ok_or_else(|| error(format!("invalid character {}", key)))
+            };
+
+            match tag {
+                "info" => font.size = number("size")?.abs(),
+                "common" => {
+                    font.line_height = number("lineHeight")?;
+                    // Line top to baseline: one below the bottom row of ink, so 30 for
+                    // MinecraftRegular's 32px cells whose letters end on row 29
+                    font.base = number("base")?;
+                    font.texture_width = number("scaleW")?;
+                    font.texture_height = number("scaleH")?;
+                }
+                "char" => {
+                    if font.texture_width <= 0.0 || font.texture_height <= 0.0 {
+                        return Err(error("char before the common line".into()));
+                    }
+                    let texture_size = Vector2::new(font.texture_width, font.texture_height);
+                    let glyph = Glyph {
+                        x_offset: number("xoffset")?,
+                        y_offset: number("yoffset")?,
+                        x_advance: number("xadvance")?,
+                        ..Glyph::from_rect(
+                            number("x")?,
+                            number("y")?,
+                            number("width")?,
+                            number("height")?,
+                            texture_size,
+                        )
+                    };
+                    font.glyphs.insert(character("id")?, glyph);
+                }
+                "kerning" => {
+                    let pair = (character("first")?, character("second")?);
+                    font.kerning.insert(pair, number("amount")?);
+                }
+                _ => {}
+            }
+        }
+
+        if font.size <= 0.0 || font.texture_width <= 0.0 || font.texture_height <= 0.0 {
+            return Err(FontError::Parse(
+                path.into(),
+                0,
+                "missing info or common line".into(),
+            ));
+        }
+        Ok(font)
+    }
+
+    // The fixed 16x16 grid from the space character on, for when the descriptor is missing
+    fn grid() -> Self {
+        let cell = 32.0;
+        let glyphs = (32..128u32)
+            .filter_map(|code| {
+                let index = code - 32;
+                let glyph = Glyph {
+                    x_advance: if code == 32 { cell * 0.48 } else { cell * 0.8 },
+                    ..Glyph::from_rect(
+                        (index % 16) as f32 * cell,
+                        (index / 16) as f32 * cell,
+                        cell,
+                        cell,
+                        Vector2::repeat(cell * 16.0),
+                    )
+                };
+                Some((char::from_u32(code)?, glyph))
+            })
+            .collect();
+        Font {
+            size: cell,
+            line_height: cell * 1.25,
+            base: cell * 0.9375,
+            texture_width: cell * 16.0,
+            texture_height: cell * 16.0,
+            glyphs,
+            kerning: HashMap::new(),
+        }
+    }
+
+    // Characters the font lacks come from the glyph atlas, then fall back to '?'
+    fn glyph(&self, c: char) -> Option<Glyph> {
+        if let Some(glyph) = self.glyphs.get(&c) {
+            return Some(*glyph);
+        }
+        match glyph_atlas::glyph(c) {
+            Some(glyph) => {
+                // Atlas glyphs sit on this font's baseline, scaled to its size
+                let scale = self.size / GLYPH_PIXELS;
+                Some(Glyph {
+                    uv_min: glyph.uv_min,
+                    uv_max: glyph.uv_max,
+                    width: glyph.width * scale,
+                    height: glyph.height * scale,
+                    x_offset: glyph.x_min * scale,
+                    y_offset: self.base - (glyph.y_min + glyph.height) * scale,
+                    x_advance: glyph.advance * scale,
+                    atlas: true,
+                })
+            }
+            None => self.glyphs.get(&'?').copied(),
+        }
+    }
+
+    fn kerning(&self, previous: Option<char>, c: char) -> f32 {
+        previous
+            .and_then(|previous| self.kerning.get(&(previous, c)))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    // Width of one line in text units, up to the right edge of its last glyph
+    fn line_width(&self, line: &str) -> f32 {
+        let mut pen = 0.0;
+        let mut width: f32 = 0.0;
+        let mut previous = None;
+        for c in line.chars() {
+            let Some(glyph) = self.glyph(c) else {
+                continue;
+            };
+            pen += self.kerning(previous, c);
+            if glyph.width > 0.0 {
+                width = width.max(pen + glyph.x_offset + glyph.width);
+            }
+            pen += glyph.x_advance;
+            previous = Some(c);
+        }
+        width / self.size
+    }
+
+    // Breaks at the last space that keeps the line within `max_width`, or inside a
+    // word that is wider on its own
+    fn wrap<'a>(&self, paragraph: &'a str, max_width: f32) -> Vec<&'a str> {
+        let mut lines = Vec::new();
+        let mut start = 0;
+        let mut space = None;
+        for (index, c) in paragraph.char_indices() {
+            if c == ' ' {
+                space = Some(index);
+                continue;
+            }
+            let end = index + c.len_utf8();
+            if index > start && self.line_width(&paragraph[start..end]) > max_width {
+                let (line_end, next) = match space {
+                    Some(space) if space > start => (space, space + 1),
+                    _ => (index, index),
+                };
+                lines.push(paragraph[start..line_end].trim_end());
+                start = next + paragraph[next..].len() - paragraph[next..].trim_start().len();
+                space = None;
+            }
+        }
+        lines.push(&paragraph[start..]);
+        lines
+    }
+}
+
+fn font() -> &'static Font {
+    FONT.get_or_init(|| {
+        Font::load(FONT_DESCRIPTOR).unwrap_or_else(|e| {
+            eprintln!("{}, using fixed-width metrics", e);
+            Font::grid()
+        })
+    })
+}
+
+// Text laid out for `Renderer::draw_text`. Sizes are in text units, one unit is
+// `scale` pixels when drawn, and the mesh starts at the bottom-left of the block.
+// It keeps its mesh, so changing the string refills the same vertex buffers
+pub struct Text {
+    pub mesh: Mesh,
+    pub width: f32,
+    pub height: f32,
+    content: String,
+    max_width: f32,
+    align: Align,
+}
+
+impl Text {
+    pub fn new(text: &str) -> Self {
+        Self::wrapped(text, f32::INFINITY, Align::Left)
+    }
+
+    // Lines break at newlines and wherever they would get wider than `max_width`
+    pub fn wrapped(text: &str, max_width: f32, align: Align) -> Self {
+        let (vertices, indices, width, height) = layout(text, max_width, align);
+        Text {
+            mesh: Mesh::new(&vertices, &indices),
+            width,
+            height,
+            content: text.into(),
+            max_width,
+            align,
+        }
+    }
+
+    pub fn set(&mut self, text: &str) {
+        self.set_wrapped(text, self.max_width, self.align);
+    }
+
+    // Lays the text out again only if something changed, so it can be called every frame
+    pub fn set_wrapped(&mut self, text: &str, max_width: f32, align: Align) {
+        if self.content == text && self.max_width == max_width && self.align == align {
+            return;
+        }
+        let (vertices, indices, width, height) = layout(text, max_width, align);
+        self.mesh.set(&vertices, &indices);
+        self.width = width;
+        self.height = height;
+        self.content.replace_range(.., text);
+        self.max_width = max_width;
+        self.align = align;
+    }
+}
+
+// Glyph quads for `Text`, with the block's width and height
+fn layout(text: &str, max_width: f32, align: Align) -> (Vec<Vertex>, Vec<u32>, f32, f32) {
+    let font = font();
+    let lines = wrap(text, max_width);
+    let widths: Vec<f32> = lines.iter().map(|line| font.line_width(line)).collect();
+    let width = widths.iter().copied().fold(0.0, f32::max);
+    let line_height = font.line_height / font.size;
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for (row, (line, line_width)) in lines.iter().zip(&widths).enumerate() {
+        let mut pen = match align {
+            Align::Left => 0.0,
+            Align::Center => (width - line_width) / 2.0 * font.size,
+        };
+        // Top of the line's glyph cells, in text units
+        let top = (lines.len() - 1 - row) as f32 * line_height + 1.0;
+        let mut previous = None;
+        for c in line.chars() {
+            let Some(glyph) = font.glyph(c) else {
+                continue;
+            };
+            pen += font.kerning(previous, c);
+            previous = Some(c);
+            if glyph.width > 0.0 && glyph.height > 0.0 {
+                let left = (pen + glyph.x_offset) / font.size;
+                let right = left + glyph.width / font.size;
+                let glyph_top = top - glyph.y_offset / font.size;
+                let bottom = glyph_top - glyph.height / font.size;
+                let (u, v) = (glyph.uv_min.x, glyph.uv_min.y);
+                let (u_right, v_top) = (glyph.uv_max.x, glyph.uv_max.y);
+                // The colour attribute is unused by text, it picks the texture instead
+                let color = if glyph.atlas {
+                    Vector3::x()
+                } else {
+                    Vector3::zeros()
+                };
+
+                let base = vertices.len() as u32;
+                vertices.extend(
+                    [
+                        (left, bottom, u, v),
+                        (right, bottom, u_right, v),
+                        (right, glyph_top, u_right, v_top),
+                        (left, glyph_top, u, v_top),
+                    ]
+                    .map(|(x, y, u, v)| Vertex {
+                        position: Vector3::new(x, y, 0.0),
+                        color,
+                        tex_coords: Vector2::new(u, v),
+                        normal: Vector3::z(),
+                    }),
+                );
+                indices.extend_from_slice(&[base, base + 1, base + 2, base + 2, base + 3, base]);
+            }
+            pen += glyph.x_advance;
+        }
+    }
+
+    let height = (lines.len() - 1) as f32 * line_height + 1.0;
+    (vertices, indices, width, height)
+}
+
+// The lines `Text::wrapped` would draw
+pub fn wrap(text: &str, max_width: f32) -> Vec<&str> {
+    let font = font();
+    text.split('\n')
+        .flat_map(|paragraph| font.wrap(paragraph, max_width))
+        .collect()
+}
+
+// Width of the widest line in text units, without building a mesh
+pub fn measure(text: &str) -> f32 {
+    let font = font();
+    text.split('\n')
+        .map(|line| font.line_width(line))
+        .fold(0.0, f32::max)
+}
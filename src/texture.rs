@@ -1,78 +1,403 @@
+use crate::resources;
 use gl::types::*;
-use std::fs::File;
-use std::io::BufReader;
+use serde::Deserialize;
+use std::cell::OnceCell;
+use std::io::{BufReader, BufWriter};
+use thiserror::Error;
 
+// From GL_ARB_texture_filter_anisotropic, core since OpenGL 4.6
+const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FF;
+
+#[derive(Error, Debug)]
+pub enum TextureError {
+    #[error("Failed to open texture {0}: {1}")]
+    Io(String, std::io::Error),
+    #[error("Invalid PNG {0}: {1}")]
+    Decode(String, png::DecodingError),
+    #[error("Unsupported PNG format in {0}: {1:?}")]
+    UnsupportedFormat(String, png::ColorType),
+    #[error("Failed to write PNG {0}: {1}")]
+    Encode(String, png::EncodingError),
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Wrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+pub struct TextureOptions {
+    pub wrap: Wrap,
+    pub filter: Filter,
+    pub mipmaps: bool,
+    // 1 disables anisotropic filtering, clamped to what the driver supports
+    pub anisotropy: f32,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self {
+            wrap: Wrap::Repeat,
+            filter: Filter::Linear,
+            mipmaps: true,
+            anisotropy: 8.0,
+        }
+    }
+}
+
+impl TextureOptions {
+    // Interface textures are drawn at a fixed size and never tile
+    pub fn ui() -> Self {
+        Self {
+            wrap: Wrap::ClampToEdge,
+            mipmaps: false,
+            anisotropy: 1.0,
+            ..Self::default()
+        }
+    }
+}
+
+// Textures made from an image keep it for the software renderer and are uploaded
+// on the first bind, so they can be loaded without a GL context
 pub struct Texture {
-    pub id: GLuint,
+    id: OnceCell<GLuint>,
+    // `None` for render targets, which only exist on the GPU
+    image: Option<(Image, TextureOptions)>,
 }
 
 impl Drop for Texture {
     fn drop(&mut self) {
-        unsafe { gl::DeleteTextures(1, &self.id) };
+        if let Some(id) = self.id.get() {
+            unsafe { gl::DeleteTextures(1, id) };
+        }
     }
 }
 
-impl Texture {
-    pub fn new(path: &str) -> Self {
-        // Load PNG file
-        let file = File::open(path).unwrap_or_else(|_| panic!("Failed to open texture: {}", path));
-        let decoder = png::Decoder::new(BufReader::new(file));
+#[derive(Clone)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    // RGBA8, bottom row first
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn load(path: &str) -> Result<Self, TextureError> {
+        let file = resources::open(path).map_err(|e| TextureError::Io(path.into(), e))?;
+        let mut decoder = png::Decoder::new(BufReader::new(file));
+        // Palette and low bit depths become 8-bit colour, 16-bit is reduced to 8
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
         let mut reader = decoder
             .read_info()
-            .unwrap_or_else(|_| panic!("Invalid PNG: {}", path));
+            .map_err(|e| TextureError::Decode(path.into(), e))?;
         let mut buf = vec![0; reader.output_buffer_size()];
         let info = reader
             .next_frame(&mut buf)
-            .unwrap_or_else(|_| panic!("Failed to read PNG frame: {}", path));
-
-        assert_eq!(
-            info.color_type,
-            png::ColorType::Rgba,
-            "Texture must be RGBA format (found {:?} in {})",
-            info.color_type,
-            path
-        );
+            .map_err(|e| TextureError::Decode(path.into(), e))?;
+        buf.truncate(info.buffer_size());
+
+        let rgba: Vec<u8> = match info.color_type {
+            png::ColorType::Rgba => buf,
+            png::ColorType::Rgb => buf
+                .chunks_exact(3)
+                .flat_map(|p| [p[0], p[1], p[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buf
+                .chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            png::ColorType::Grayscale => buf.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+            png::ColorType::Indexed => {
+                return Err(TextureError::UnsupportedFormat(
+                    path.into(),
+                    info.color_type,
+                ))
+            }
+        };
 
         // Flip image vertically (OpenGL expects origin at bottom-left)
-        let mut flipped = Vec::with_capacity(buf.len());
-        let bytes_per_row = info.width as usize * 4; // 4 bytes per pixel (RGBA)
-        for row in (0..info.height as usize).rev() {
-            let start = row * bytes_per_row;
-            let end = start + bytes_per_row;
-            flipped.extend_from_slice(&buf[start..end]); // Will panic here if format is wrong
+        let bytes_per_row = info.width as usize * 4;
+        let pixels = rgba
+            .chunks_exact(bytes_per_row)
+            .rev()
+            .flatten()
+            .copied()
+            .collect();
+
+        Ok(Image {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+
+    // Written as an RGBA PNG at a plain path, not through `resources`
+    pub fn save(&self, path: &str) -> Result<(), TextureError> {
+        let file = std::fs::File::create(path).map_err(|e| TextureError::Io(path.into(), e))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let encode = |e| TextureError::Encode(path.into(), e);
+        let mut writer = encoder.write_header().map_err(encode)?;
+        // PNG stores the top row first
+        let rows: Vec<u8> = self
+            .pixels
+            .chunks_exact(self.width as usize * 4)
+            .rev()
+            .flatten()
+            .copied()
+            .collect();
+        writer.write_image_data(&rows).map_err(encode)?;
+        writer.finish().map_err(encode)
+    }
+
+    // Magenta checkerboard shown in place of missing textures
+    pub fn placeholder() -> Self {
+        let (magenta, black) = ([255, 0, 255, 255], [0, 0, 0, 255]);
+        Image {
+            width: 2,
+            height: 2,
+            pixels: [magenta, black, black, magenta].concat(),
+        }
+    }
+
+    // Bilinear resample, used to fit images of different sizes in one array
+    pub fn resized(&self, width: u32, height: u32) -> Image {
+        if (width, height) == (self.width, self.height) {
+            return Image {
+                width,
+                height,
+                pixels: self.pixels.clone(),
+            };
         }
 
-        // Create OpenGL texture
-        let mut texture_id = 0;
+        let texel =
+            |x: u32, y: u32, c: usize| self.pixels[((y * self.width + x) * 4) as usize + c] as f32;
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            let sy = ((y as f32 + 0.5) * self.height as f32 / height as f32 - 0.5).max(0.0);
+            let (y0, fy) = (sy as u32, sy.fract());
+            let y1 = (y0 + 1).min(self.height - 1);
+            for x in 0..width {
+                let sx = ((x as f32 + 0.5) * self.width as f32 / width as f32 - 0.5).max(0.0);
+                let (x0, fx) = (sx as u32, sx.fract());
+                let x1 = (x0 + 1).min(self.width - 1);
+                for c in 0..4 {
+                    let top = texel(x0, y0, c) * (1.0 - fx) + texel(x1, y0, c) * fx;
+                    let bottom = texel(x0, y1, c) * (1.0 - fx) + texel(x1, y1, c) * fx;
+                    pixels.push((top * (1.0 - fy) + bottom * fy).round() as u8);
+                }
+            }
+        }
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+impl Texture {
+    pub fn placeholder() -> Self {
+        let options = TextureOptions {
+            filter: Filter::Nearest,
+            mipmaps: false,
+            ..TextureOptions::default()
+        };
+        Self::from_image(Image::placeholder(), &options)
+    }
+
+    pub fn from_image(image: Image, options: &TextureOptions) -> Self {
+        Self {
+            id: OnceCell::new(),
+            image: Some((image, *options)),
+        }
+    }
+
+    // Wraps a texture created directly in GL, such as a framebuffer attachment
+    pub fn from_id(id: GLuint) -> Self {
+        Self {
+            id: OnceCell::from(id),
+            image: None,
+        }
+    }
+
+    pub fn image(&self) -> Option<(&Image, &TextureOptions)> {
+        self.image.as_ref().map(|(image, options)| (image, options))
+    }
+
+    pub fn id(&self) -> GLuint {
+        *self.id.get_or_init(|| match &self.image {
+            Some((image, options)) => upload(image, options),
+            None => 0,
+        })
+    }
+
+    pub fn bind(&self, unit: u32) {
+        let id = self.id();
         unsafe {
-            gl::GenTextures(1, &mut texture_id);
-            gl::BindTexture(gl::TEXTURE_2D, texture_id);
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+        }
+    }
+}
+
+fn upload(image: &Image, options: &TextureOptions) -> GLuint {
+    let mut texture_id = 0;
+    unsafe {
+        gl::GenTextures(1, &mut texture_id);
+        gl::BindTexture(gl::TEXTURE_2D, texture_id);
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA as i32,
+            image.width as i32,
+            image.height as i32,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            image.pixels.as_ptr() as *const _,
+        );
+        apply_options(gl::TEXTURE_2D, options);
+    }
+    texture_id
+}
+
+// Layers of equal size sampled with a single bind, images are resized to the largest.
+// Uploaded on the first bind like `Texture`
+pub struct TextureArray {
+    id: OnceCell<GLuint>,
+    layers: Vec<Image>,
+    options: TextureOptions,
+}
+
+impl Drop for TextureArray {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.get() {
+            unsafe { gl::DeleteTextures(1, id) };
+        }
+    }
+}
+
+impl TextureArray {
+    pub fn new(images: &[Image], options: &TextureOptions) -> Self {
+        let width = images.iter().map(|image| image.width).max().unwrap_or(1);
+        let height = images.iter().map(|image| image.height).max().unwrap_or(1);
+        Self {
+            id: OnceCell::new(),
+            layers: images
+                .iter()
+                .map(|image| image.resized(width, height))
+                .collect(),
+            options: *options,
+        }
+    }
 
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+    pub fn layer(&self, index: usize) -> Option<&Image> {
+        self.layers.get(index)
+    }
+
+    pub fn options(&self) -> &TextureOptions {
+        &self.options
+    }
 
-            gl::TexImage2D(
-                gl::TEXTURE_2D,
+    fn upload(&self) -> GLuint {
+        let (width, height) = self
+            .layers
+            .first()
+            .map_or((1, 1), |image| (image.width, image.height));
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, id);
+            gl::TexImage3D(
+                gl::TEXTURE_2D_ARRAY,
                 0,
-                gl::RGBA as i32,
-                info.width as i32,
-                info.height as i32,
+                gl::RGBA8 as i32,
+                width as i32,
+                height as i32,
+                self.layers.len() as i32,
                 0,
                 gl::RGBA,
                 gl::UNSIGNED_BYTE,
-                flipped.as_ptr() as *const _,
+                std::ptr::null(),
             );
+            for (layer, image) in self.layers.iter().enumerate() {
+                gl::TexSubImage3D(
+                    gl::TEXTURE_2D_ARRAY,
+                    0,
+                    0,
+                    0,
+                    layer as i32,
+                    width as i32,
+                    height as i32,
+                    1,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    image.pixels.as_ptr() as *const _,
+                );
+            }
+            apply_options(gl::TEXTURE_2D_ARRAY, &self.options);
         }
-
-        Texture { id: texture_id }
+        id
     }
 
     pub fn bind(&self, unit: u32) {
+        let id = *self.id.get_or_init(|| self.upload());
         unsafe {
             gl::ActiveTexture(gl::TEXTURE0 + unit);
-            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, id);
+        }
+    }
+}
+
+// Sets wrapping and filtering on the bound texture, generating mipmaps if asked
+pub unsafe fn apply_options(target: GLenum, options: &TextureOptions) {
+    let wrap = match options.wrap {
+        Wrap::Repeat => gl::REPEAT,
+        Wrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+        Wrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+    };
+    let (min_filter, mag_filter) = match (options.filter, options.mipmaps) {
+        (Filter::Nearest, false) => (gl::NEAREST, gl::NEAREST),
+        (Filter::Nearest, true) => (gl::NEAREST_MIPMAP_NEAREST, gl::NEAREST),
+        (Filter::Linear, false) => (gl::LINEAR, gl::LINEAR),
+        // Trilinear
+        (Filter::Linear, true) => (gl::LINEAR_MIPMAP_LINEAR, gl::LINEAR),
+    };
+
+    gl::TexParameteri(target, gl::TEXTURE_WRAP_S, wrap as i32);
+    gl::TexParameteri(target, gl::TEXTURE_WRAP_T, wrap as i32);
+    gl::TexParameteri(target, gl::TEXTURE_MIN_FILTER, min_filter as i32);
+    gl::TexParameteri(target, gl::TEXTURE_MAG_FILTER, mag_filter as i32);
+    if options.mipmaps {
+        gl::GenerateMipmap(target);
+    }
+
+    if options.anisotropy > 1.0 {
+        let mut max_anisotropy = 0.0;
+        gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut max_anisotropy);
+        if max_anisotropy >= 1.0 {
+            gl::TexParameterf(
+                target,
+                TEXTURE_MAX_ANISOTROPY,
+                options.anisotropy.min(max_anisotropy),
+            );
+        } else {
+            // Not supported, discard the INVALID_ENUM from the query
+            gl::GetError();
         }
     }
 }
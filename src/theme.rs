@@ -0,0 +1,83 @@
+use crate::assets::{AssetManager, Handle};
+use crate::level::ObstacleType;
+use crate::map_select::Maps;
+use crate::texture::{TextureArray, TextureOptions};
+use std::cell::Ref;
+
+// Layers of a map theme, in the order of the texture array
+#[derive(Clone, Copy)]
+pub enum Layer {
+    Floor,
+    Wall,
+    Ceiling,
+    Cube,
+    LowBar,
+    TallWall,
+    HighBar,
+}
+
+impl Layer {
+    const ALL: [Layer; 7] = [
+        Layer::Floor,
+        Layer::Wall,
+        Layer::Ceiling,
+        Layer::Cube,
+        Layer::LowBar,
+        Layer::TallWall,
+        Layer::HighBar,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Layer::Floor => "floor",
+            Layer::Wall => "wall",
+            Layer::Ceiling => "ceiling",
+            Layer::Cube => "cube",
+            Layer::LowBar => "lowBar",
+            Layer::TallWall => "tallWall",
+            Layer::HighBar => "highBar",
+        }
+    }
+
+    pub fn obstacle(obstacle_type: &ObstacleType) -> Self {
+        match obstacle_type {
+            ObstacleType::Cube => Layer::Cube,
+            ObstacleType::LowBar => Layer::LowBar,
+            ObstacleType::TallWall => Layer::TallWall,
+            ObstacleType::HighBar => Layer::HighBar,
+        }
+    }
+
+    pub fn index(self) -> f32 {
+        self as u32 as f32
+    }
+}
+
+// All the textures of a map packed into one array, bound once per frame
+pub struct Theme {
+    textures: Handle<TextureArray>,
+}
+
+impl Theme {
+    pub fn load(assets: &mut AssetManager, map: &Maps, options: &TextureOptions) -> Self {
+        let map_path = match map {
+            Maps::Campus(path) | Maps::Cave(path) | Maps::Temple(path) => {
+                format!("assets/textures/maps/{}", path)
+            }
+            Maps::None => String::from("assets/textures/maps/campus"),
+        };
+
+        let paths: Vec<String> = Layer::ALL
+            .iter()
+            .map(|layer| format!("{}/{}.png", map_path, layer.name()))
+            .collect();
+
+        Theme {
+            textures: assets.texture_array(&paths, options),
+        }
+    }
+
+    pub fn textures(&self) -> Ref<'_, TextureArray> {
+        self.textures.get()
+    }
+}
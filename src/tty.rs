@@ -0,0 +1,273 @@
+use crate::audio::AudioSystem;
+use crate::camera::{Camera, CameraMode, CameraSettings};
+use crate::character::Character;
+use crate::game::{Collision, Run};
+use crate::level::{LevelGenerator, ObstacleType};
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+// Plays in the terminal, seen from above with the three lanes as columns and the
+// track ahead as rows. Runs the same simulation as the window, without GL or sound
+const LANE_COLUMNS: usize = 5;
+const TRACK_WIDTH: f32 = 3.0 * LevelGenerator::LANE_WIDTH;
+const ROWS: usize = 24;
+// Units of track per row
+const ROW_DEPTH: f32 = 3.0;
+const TICK: f32 = 1.0 / 60.0;
+const FRAME: Duration = Duration::from_millis(33);
+// Terminals only send key presses, holding a key repeats them
+const SLIDE_HOLD: f32 = 0.4;
+
+enum Input {
+    Left,
+    Right,
+    Jump,
+    Slide,
+    Retry,
+    Quit,
+}
+
+// Keys are read from stdin without echo or line buffering until this is dropped
+struct RawMode {
+    original: libc::termios,
+}
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        unsafe {
+            let mut original = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut raw = original;
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+            raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+            // Reads return at once, with whatever was typed since the last one
+            raw.c_cc[libc::VMIN] = 0;
+            raw.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { original })
+        }
+    }
+
+    fn read(&self) -> Vec<u8> {
+        let mut buffer = [0u8; 64];
+        let read = unsafe {
+            libc::read(
+                libc::STDIN_FILENO,
+                buffer.as_mut_ptr() as *mut libc::c_void,
+                buffer.len(),
+            )
+        };
+        buffer[..read.max(0) as usize].to_vec()
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+        // Show the cursor again and leave the alternate screen
+        print!("\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+    }
+}
+
+fn parse_input(bytes: &[u8]) -> Vec<Input> {
+    let mut inputs = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let input = match bytes[i..] {
+            // Arrow keys
+            [0x1b, b'[', b'A', ..] | [0x1b, b'O', b'A', ..] => Some(Input::Jump),
+            [0x1b, b'[', b'B', ..] | [0x1b, b'O', b'B', ..] => Some(Input::Slide),
+            [0x1b, b'[', b'C', ..] | [0x1b, b'O', b'C', ..] => Some(Input::Right),
+            [0x1b, b'[', b'D', ..] | [0x1b, b'O', b'D', ..] => Some(Input::Left),
+            [0x1b, b'[', ..] | [0x1b, b'O', ..] => None,
+            [0x1b, ..] | [b'q', ..] | [b'Q', ..] | [0x03, ..] => Some(Input::Quit),
+            [b'a', ..] | [b'A', ..] => Some(Input::Left),
+            [b'd', ..] | [b'D', ..] => Some(Input::Right),
+            [b'w', ..] | [b'W', ..] | [b' ', ..] => Some(Input::Jump),
+            [b's', ..] | [b'S', ..] => Some(Input::Slide),
+            [b'r', ..] | [b'R', ..] | [b'\r', ..] | [b'\n', ..] => Some(Input::Retry),
+            _ => None,
+        };
+        // Escape sequences are three bytes, anything unknown in one is skipped
+        i += match bytes[i..] {
+            [0x1b, b'[' | b'O', _, ..] => 3,
+            [0x1b, b'[' | b'O'] => 2,
+            _ => 1,
+        };
+        inputs.extend(input);
+    }
+    inputs
+}
+
+pub fn run(seed: Option<u64>) {
+    let raw_mode = match RawMode::enable() {
+        Ok(raw_mode) => raw_mode,
+        Err(e) => {
+            eprintln!("--tty needs an interactive terminal: {}", e);
+            return;
+        }
+    };
+    // Alternate screen, hidden cursor
+    print!("\x1b[?1049h\x1b[?25l");
+
+    let audio = AudioSystem::silent();
+    let new_run = || {
+        let camera = Camera::new(CameraSettings::default(), CameraMode::default());
+        Run::new(seed.unwrap_or_else(rand::random), camera)
+    };
+    let mut run = new_run();
+    let mut character = Character::new();
+    let mut crashed = false;
+    let mut best = 0;
+    let mut slide_time = 0.0;
+    let mut near_miss_time = 0.0;
+    let mut last_time = Instant::now();
+    let mut accumulator = 0.0;
+
+    loop {
+        for input in parse_input(&raw_mode.read()) {
+            match input {
+                Input::Quit => return,
+                Input::Retry if crashed => {
+                    run = new_run();
+                    character = Character::new();
+                    crashed = false;
+                    slide_time = 0.0;
+                    near_miss_time = 0.0;
+                }
+                _ if crashed => {}
+                Input::Left => character.move_left(&audio),
+                Input::Right => character.move_right(&audio),
+                Input::Jump => character.jump(&audio),
+                Input::Slide => {
+                    character.move_down(true);
+                    slide_time = SLIDE_HOLD;
+                }
+                Input::Retry => {}
+            }
+        }
+
+        let now = Instant::now();
+        accumulator += (now - last_time).as_secs_f32();
+        last_time = now;
+        while accumulator >= TICK && !crashed {
+            accumulator -= TICK;
+            if slide_time > 0.0 {
+                slide_time -= TICK;
+                if slide_time <= 0.0 {
+                    character.move_down(false);
+                }
+            }
+            near_miss_time = (near_miss_time - TICK).max(0.0);
+            match run.advance(&mut character, TICK) {
+                Collision::Hit => {
+                    crashed = true;
+                    best = best.max(run.z as i32 / 10);
+                }
+                Collision::NearMiss => near_miss_time = 0.5,
+                Collision::None => {}
+            }
+        }
+        if crashed {
+            accumulator = 0.0;
+        }
+
+        let status = if crashed {
+            "CRASHED - r to retry, q to quit"
+        } else if near_miss_time > 0.0 {
+            "close!"
+        } else {
+            ""
+        };
+        let frame = draw(&run, &character, best, status);
+        let mut stdout = io::stdout();
+        if stdout
+            .write_all(frame.as_bytes())
+            .and_then(|_| stdout.flush())
+            .is_err()
+        {
+            return;
+        }
+        std::thread::sleep(FRAME.saturating_sub(last_time.elapsed()));
+    }
+}
+
+// Column for an x position, the left lane (positive x) first
+fn column(x: f32) -> f32 {
+    (TRACK_WIDTH / 2.0 - x) / TRACK_WIDTH * (3 * LANE_COLUMNS) as f32
+}
+
+fn obstacle_glyph(obstacle_type: &ObstacleType) -> char {
+    match obstacle_type {
+        ObstacleType::Cube => '#',
+        ObstacleType::TallWall => 'H',
+        ObstacleType::LowBar => '=',
+        ObstacleType::HighBar => '-',
+    }
+}
+
+fn draw(run: &Run, character: &Character, best: i32, status: &str) -> String {
+    let width = 3 * LANE_COLUMNS;
+    let mut grid = vec![vec![' '; width]; ROWS];
+
+    // Lane markings, the rows scroll with the distance
+    let scroll = (run.z / ROW_DEPTH) as usize;
+    for (row, cells) in grid.iter_mut().enumerate() {
+        if (row + scroll).is_multiple_of(2) {
+            for lane in 1..3 {
+                cells[lane * LANE_COLUMNS] = '.';
+            }
+        }
+    }
+
+    for segment in run.level.segments() {
+        for obstacle in &segment.obstacles {
+            let ahead = obstacle.position.z - run.z;
+            let row = (ahead / ROW_DEPTH).round();
+            if row < 0.0 || row >= ROWS as f32 {
+                continue;
+            }
+            let aabb = obstacle.get_aabb();
+            let first = column(aabb.max.x).round().max(0.0) as usize;
+            let last = (column(aabb.min.x).round() as usize).min(width);
+            let glyph = obstacle_glyph(&obstacle.obstacle_type);
+            for cell in &mut grid[row as usize][first..last] {
+                *cell = glyph;
+            }
+        }
+    }
+
+    let player = if character.position.y > 0.05 {
+        '^'
+    } else if character.is_sliding() {
+        'v'
+    } else {
+        '@'
+    };
+    let player_column = (column(character.position.x) as usize).min(width - 1);
+    grid[0][player_column] = player;
+
+    // Cursor home, then every line cleared to its end
+    let mut frame = String::from("\x1b[H");
+    frame += &format!(
+        "42run  {}m  best {}m  speed {:.0}\x1b[K\n",
+        run.z as i32 / 10,
+        best,
+        run.speed
+    );
+    for cells in grid.iter().rev() {
+        let line: String = cells.iter().collect();
+        frame += &format!("|{}|\x1b[K\n", line);
+    }
+    frame += &format!("{}\x1b[K\n", status);
+    frame += "# cube  H wall  = jump  - slide\x1b[K\n";
+    frame += "move a/d  jump w/space  slide s  quit q\x1b[K";
+    frame
+}
@@ -0,0 +1,429 @@
+use crate::audio::AudioSystem;
+use crate::renderer::Renderer;
+use crate::text::{Align, Text};
+use nalgebra::Vector3;
+
+// Widgets the screens are built from. Positions are in pixels from the bottom-left
+// corner like `Renderer`, relative to an anchor on the screen, so layouts follow
+// the window when it's resized
+pub const TEXT_COLOR: Vector3<f32> = Vector3::new(0.1, 0.0, 0.0);
+pub const TITLE_COLOR: Vector3<f32> = Vector3::new(0.5, 0.3, 0.7);
+
+#[derive(Clone, Copy)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    Center,
+    Bottom,
+}
+
+impl Anchor {
+    // Where the anchor sits across the screen, and across the widget it places
+    fn factors(self) -> (f32, f32) {
+        match self {
+            Anchor::TopLeft => (0.0, 1.0),
+            Anchor::Top => (0.5, 1.0),
+            Anchor::Center => (0.5, 0.5),
+            Anchor::Bottom => (0.5, 0.0),
+        }
+    }
+}
+
+// A widget's rectangle: the same anchor point of the widget and of the screen are
+// `offset` apart
+#[derive(Clone, Copy)]
+pub struct Placement {
+    pub anchor: Anchor,
+    pub offset: (f32, f32),
+    pub size: (f32, f32),
+}
+
+impl Placement {
+    pub fn new(anchor: Anchor, offset: (f32, f32), size: (f32, f32)) -> Self {
+        Self {
+            anchor,
+            offset,
+            size,
+        }
+    }
+
+    // Bottom-left corner on a screen of the given size
+    pub fn position(&self, screen: (f32, f32)) -> (f32, f32) {
+        let (x, y) = self.anchor.factors();
+        (
+            (screen.0 - self.size.0) * x + self.offset.0,
+            (screen.1 - self.size.1) * y + self.offset.1,
+        )
+    }
+
+    fn contains(&self, screen: (f32, f32), point: (f32, f32)) -> bool {
+        let (x, y) = self.position(screen);
+        point.0 >= x && point.0 <= x + self.size.0 && point.1 >= y && point.1 <= y + self.size.1
+    }
+}
+
+// Cells of one size in rows, filled from the top-left, with the whole block placed
+// like a widget. One column makes a list
+pub struct Grid {
+    pub anchor: Anchor,
+    pub offset: (f32, f32),
+    pub columns: usize,
+    pub cell: (f32, f32),
+    pub spacing: (f32, f32),
+}
+
+impl Grid {
+    // Placement of cell `index` in a grid of `count` cells
+    pub fn placement(&self, index: usize, count: usize) -> Placement {
+        let rows = count.div_ceil(self.columns);
+        let step = (self.cell.0 + self.spacing.0, self.cell.1 + self.spacing.1);
+        let block = (
+            self.columns.min(count) as f32 * step.0 - self.spacing.0,
+            rows as f32 * step.1 - self.spacing.1,
+        );
+        let (column, row) = (index % self.columns, index / self.columns);
+        let corner = (column as f32 * step.0, (rows - 1 - row) as f32 * step.1);
+        // Cell offset from the same anchor, so it moves with the block
+        let (x, y) = self.anchor.factors();
+        Placement::new(
+            self.anchor,
+            (
+                self.offset.0 + corner.0 - (block.0 - self.cell.0) * x,
+                self.offset.1 + corner.1 - (block.1 - self.cell.1) * y,
+            ),
+            self.cell,
+        )
+    }
+}
+
+// The left mouse button for one frame, in the same coordinates as the renderer
+#[derive(Default)]
+pub struct Pointer {
+    pub position: (f32, f32),
+    pub down: bool,
+    // The button went down or up during this frame
+    pub pressed: bool,
+    pub released: bool,
+}
+
+impl Pointer {
+    pub fn press(&mut self) {
+        self.down = true;
+        self.pressed = true;
+    }
+
+    pub fn release(&mut self) {
+        self.down = false;
+        self.released = true;
+    }
+
+    // Clears the presses and releases seen by the last frame
+    pub fn next_frame(&mut self) {
+        self.pressed = false;
+        self.released = false;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+enum State {
+    #[default]
+    Idle,
+    Hovered,
+    Pressed,
+}
+
+// Hover and press tracking shared by the clickable widgets. A click is a press and
+// a release both over the widget
+#[derive(Default)]
+struct Interaction {
+    state: State,
+    // The press started over this widget
+    armed: bool,
+}
+
+impl Interaction {
+    // Whether the widget was clicked this frame
+    fn update(&mut self, placement: &Placement, pointer: &Pointer, screen: (f32, f32)) -> bool {
+        let hovered = placement.contains(screen, pointer.position);
+        if pointer.pressed {
+            self.armed = hovered;
+        }
+        let clicked = pointer.released && self.armed && hovered;
+        if !pointer.down {
+            self.armed = false;
+        }
+        self.state = match (hovered, self.armed) {
+            (true, true) => State::Pressed,
+            (true, false) => State::Hovered,
+            _ => State::Idle,
+        };
+        clicked
+    }
+
+    fn shade(&self, color: Vector3<f32>) -> Vector3<f32> {
+        match self.state {
+            State::Idle => color,
+            State::Hovered => (color * 1.2).map(|channel| channel.min(1.0)),
+            State::Pressed => color * 0.75,
+        }
+    }
+}
+
+// Text on its own, placed by its size
+pub struct Label {
+    pub anchor: Anchor,
+    pub offset: (f32, f32),
+    pub scale: f32,
+    pub color: Vector3<f32>,
+    pub text: Text,
+}
+
+impl Label {
+    pub fn new(text: &str, anchor: Anchor, offset: (f32, f32), scale: f32) -> Self {
+        Self {
+            anchor,
+            offset,
+            scale,
+            color: Vector3::new(1.0, 1.0, 1.0),
+            text: Text::new(text),
+        }
+    }
+
+    // Screen titles share a place, size and colour
+    pub fn title(text: &str) -> Self {
+        Self::new(text, Anchor::Top, (0.0, -94.0), 60.0).color(TITLE_COLOR)
+    }
+
+    pub fn color(mut self, color: Vector3<f32>) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.text.set(text);
+    }
+
+    // Wraps at `max_width` pixels
+    pub fn set_wrapped(&mut self, text: &str, max_width: f32, align: Align) {
+        self.text.set_wrapped(text, max_width / self.scale, align);
+    }
+
+    pub fn size(&self) -> (f32, f32) {
+        (self.text.width * self.scale, self.text.height * self.scale)
+    }
+
+    pub fn render(&self, renderer: &mut dyn Renderer) {
+        let placement = Placement::new(self.anchor, self.offset, self.size());
+        let position = placement.position(renderer.size());
+        renderer.draw_text(&self.text.mesh, position, self.scale, self.color);
+    }
+}
+
+// A plain rectangle behind other widgets
+pub struct Panel {
+    pub placement: Placement,
+    pub color: Vector3<f32>,
+}
+
+impl Panel {
+    pub fn new(placement: Placement, color: Vector3<f32>) -> Self {
+        Self { placement, color }
+    }
+
+    pub fn render(&self, renderer: &mut dyn Renderer) {
+        let position = self.placement.position(renderer.size());
+        renderer.draw_quad(position, self.placement.size, self.color);
+    }
+}
+
+pub struct Button {
+    pub placement: Placement,
+    pub color: Vector3<f32>,
+    // Played on click, `None` for a silent button
+    pub sound: Option<&'static str>,
+    text: Text,
+    text_scale: f32,
+    interaction: Interaction,
+}
+
+impl Button {
+    pub fn new(text: &str, placement: Placement, color: Vector3<f32>) -> Self {
+        Self {
+            placement,
+            color,
+            sound: Some("button1"),
+            text: Text::new(text),
+            text_scale: 50.0,
+            interaction: Interaction::default(),
+        }
+    }
+
+    pub fn text_scale(mut self, scale: f32) -> Self {
+        self.text_scale = scale;
+        self
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.text.set(text);
+    }
+
+    // Whether the button was clicked this frame, its sound is played if so
+    pub fn update(&mut self, pointer: &Pointer, screen: (f32, f32), audio: &AudioSystem) -> bool {
+        let clicked = self.interaction.update(&self.placement, pointer, screen);
+        if clicked {
+            if let Some(sound) = self.sound {
+                audio.play_sound(sound);
+            }
+        }
+        clicked
+    }
+
+    pub fn render(&self, renderer: &mut dyn Renderer) {
+        let (x, y) = self.placement.position(renderer.size());
+        let (width, height) = self.placement.size;
+        renderer.draw_quad((x, y), (width, height), self.interaction.shade(self.color));
+        // Centred on the button
+        let scale = self.text_scale;
+        renderer.draw_text(
+            &self.text.mesh,
+            (
+                x + width / 2.0 - self.text.width * scale / 2.0,
+                y + height / 2.0 - self.text.height * scale / 2.0,
+            ),
+            scale,
+            TEXT_COLOR,
+        );
+    }
+}
+
+// Updates every button and gives the action paired with the one clicked, if any
+pub fn clicked<A: Copy>(
+    buttons: &mut [(A, Button)],
+    pointer: &Pointer,
+    screen: (f32, f32),
+    audio: &AudioSystem,
+) -> Option<A> {
+    let mut clicked = None;
+    for (action, button) in buttons {
+        if button.update(pointer, screen, audio) {
+            clicked = Some(*action);
+        }
+    }
+    clicked
+}
+
+// A button showing "NAME: ON" or "NAME: OFF" that flips on click
+pub struct Toggle {
+    pub value: bool,
+    name: String,
+    button: Button,
+}
+
+impl Toggle {
+    pub fn new(name: &str, value: bool, button: Button) -> Self {
+        let mut toggle = Self {
+            value,
+            name: name.into(),
+            button,
+        };
+        toggle.set(value);
+        toggle
+    }
+
+    pub fn set(&mut self, value: bool) {
+        self.value = value;
+        let state = if value { "ON" } else { "OFF" };
+        self.button.set_text(&format!("{}: {}", self.name, state));
+    }
+
+    // Whether the value changed this frame
+    pub fn update(&mut self, pointer: &Pointer, screen: (f32, f32), audio: &AudioSystem) -> bool {
+        let clicked = self.button.update(pointer, screen, audio);
+        if clicked {
+            self.set(!self.value);
+        }
+        clicked
+    }
+
+    pub fn render(&self, renderer: &mut dyn Renderer) {
+        self.button.render(renderer);
+    }
+}
+
+// A value from 0 to 1, set by pressing or dragging along the bar
+pub struct Slider {
+    pub placement: Placement,
+    pub color: Vector3<f32>,
+    pub value: f32,
+    name: String,
+    text: Text,
+    text_scale: f32,
+    interaction: Interaction,
+}
+
+impl Slider {
+    const HANDLE_WIDTH: f32 = 8.0;
+
+    pub fn new(name: &str, value: f32, placement: Placement, color: Vector3<f32>) -> Self {
+        let mut slider = Self {
+            placement,
+            color,
+            value,
+            name: name.into(),
+            text: Text::new(""),
+            text_scale: 30.0,
+            interaction: Interaction::default(),
+        };
+        slider.set(value);
+        slider
+    }
+
+    pub fn set(&mut self, value: f32) {
+        self.value = value.clamp(0.0, 1.0);
+        let percent = (self.value * 100.0).round();
+        self.text.set(&format!("{}: {}%", self.name, percent));
+    }
+
+    // Whether the value changed this frame. The click sound plays once the handle is
+    // let go, as a sample of a volume
+    pub fn update(&mut self, pointer: &Pointer, screen: (f32, f32), audio: &AudioSystem) -> bool {
+        let held = self.interaction.armed;
+        let clicked = self.interaction.update(&self.placement, pointer, screen);
+        // Drags keep going when the pointer leaves the bar
+        let released = pointer.released && (held || clicked);
+        if !(self.interaction.armed || released) {
+            return false;
+        }
+        if released {
+            audio.play_sound("button1");
+        }
+        let (x, _) = self.placement.position(screen);
+        let value = ((pointer.position.0 - x) / self.placement.size.0).clamp(0.0, 1.0);
+        let changed = value != self.value;
+        self.set(value);
+        changed
+    }
+
+    pub fn render(&self, renderer: &mut dyn Renderer) {
+        let (x, y) = self.placement.position(renderer.size());
+        let (width, height) = self.placement.size;
+        let color = self.interaction.shade(self.color);
+        renderer.draw_quad((x, y), (width, height), color * 0.5);
+        renderer.draw_quad((x, y), (width * self.value, height), color);
+        renderer.draw_quad(
+            (x + (width - Self::HANDLE_WIDTH) * self.value, y),
+            (Self::HANDLE_WIDTH, height),
+            Vector3::new(0.9, 0.9, 0.9),
+        );
+        let scale = self.text_scale;
+        renderer.draw_text(
+            &self.text.mesh,
+            (
+                x + width / 2.0 - self.text.width * scale / 2.0,
+                y + height / 2.0 - self.text.height * scale / 2.0,
+            ),
+            scale,
+            TEXT_COLOR,
+        );
+    }
+}